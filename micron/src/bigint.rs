@@ -0,0 +1,322 @@
+use crate::parser::Value;
+use std::cmp::Ordering;
+use std::convert::TryFrom;
+use std::fmt;
+use std::ops::{Add, Mul, Neg, Sub};
+
+/// Every digit holds 9 decimal digits, which makes parsing and printing straightforward.
+const BASE: u64 = 1_000_000_000;
+
+/// An arbitrary-precision integer, used for the Ints that don't fit in an isize.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BigInt {
+    negative: bool,
+    /// The least significant digit comes first, there are no leading zeros and zero has no digits.
+    digits: Vec<u32>,
+}
+
+impl BigInt {
+    /// Parses an optionally negative run of decimal digits.
+    pub fn parse(s: &str) -> Option<Self> {
+        let (negative, s) = match s.strip_prefix('-') {
+            Some(rest) => (true, rest),
+            None => (false, s),
+        };
+
+        if s.is_empty() || !s.bytes().all(|b| b.is_ascii_digit()) {
+            return None;
+        }
+
+        let bytes = s.as_bytes();
+        let mut digits = Vec::new();
+        let mut end = bytes.len();
+
+        while end > 0 {
+            let start = end.saturating_sub(9);
+            let chunk = std::str::from_utf8(&bytes[start..end]).unwrap();
+            digits.push(chunk.parse::<u32>().unwrap());
+            end = start;
+        }
+
+        Some(Self::new(negative, digits))
+    }
+
+    fn new(negative: bool, mut digits: Vec<u32>) -> Self {
+        while digits.last() == Some(&0) {
+            digits.pop();
+        }
+
+        Self {
+            negative: negative && !digits.is_empty(),
+            digits,
+        }
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.digits.is_empty()
+    }
+
+    pub fn to_isize(&self) -> Option<isize> {
+        let mut n: i128 = 0;
+
+        for &digit in self.digits.iter().rev() {
+            n = n.checked_mul(BASE as i128)?.checked_add(digit as i128)?;
+        }
+
+        if self.negative {
+            n = -n;
+        }
+
+        isize::try_from(n).ok()
+    }
+
+    pub fn abs(&self) -> Self {
+        Self::new(false, self.digits.clone())
+    }
+
+    /// Divides rounding towards zero, the remainder takes the sign of `self` just like the Ints.
+    /// Returns None when dividing by zero.
+    pub fn div_rem(&self, other: &Self) -> Option<(Self, Self)> {
+        if other.is_zero() {
+            return None;
+        }
+
+        let mut quotient = vec![0u32; self.digits.len()];
+        let mut remainder = Vec::new();
+
+        for (i, &digit) in self.digits.iter().enumerate().rev() {
+            remainder.insert(0, digit);
+            remainder = Self::new(false, remainder).digits;
+
+            // the largest digit that still fits, found with a binary search
+            let (mut low, mut high) = (0u32, (BASE - 1) as u32);
+            while low < high {
+                let mid = high - (high - low) / 2;
+                match cmp_digits(&mul_digit(&other.digits, mid), &remainder) {
+                    Ordering::Greater => high = mid - 1,
+                    _ => low = mid,
+                }
+            }
+
+            quotient[i] = low;
+            remainder = sub_digits(&remainder, &mul_digit(&other.digits, low));
+        }
+
+        Some((
+            Self::new(self.negative != other.negative, quotient),
+            Self::new(self.negative, remainder),
+        ))
+    }
+
+    pub fn pow(&self, mut exp: u32) -> Self {
+        let mut base = self.clone();
+        let mut result = Self::new(false, vec![1]);
+
+        while exp > 0 {
+            if exp & 1 == 1 {
+                result = &result * &base;
+            }
+            exp >>= 1;
+            if exp > 0 {
+                base = &base * &base;
+            }
+        }
+
+        result
+    }
+
+    /// Roughly the number of (base 10^9) digits the power would take,
+    /// used to refuse absurdly large results before computing them.
+    pub fn pow_size(&self, exp: u32) -> f64 {
+        match self.digits.split_last() {
+            Some((&last, rest)) => {
+                (rest.len() as f64 + (last as f64 + 1.0).log10() / 9.0) * exp as f64
+            }
+            None => 0.0,
+        }
+    }
+}
+
+impl Neg for &BigInt {
+    type Output = BigInt;
+
+    fn neg(self) -> BigInt {
+        BigInt::new(!self.negative, self.digits.clone())
+    }
+}
+
+impl Add for &BigInt {
+    type Output = BigInt;
+
+    fn add(self, other: Self) -> BigInt {
+        if self.negative == other.negative {
+            return BigInt::new(self.negative, add_digits(&self.digits, &other.digits));
+        }
+
+        match cmp_digits(&self.digits, &other.digits) {
+            Ordering::Less => BigInt::new(other.negative, sub_digits(&other.digits, &self.digits)),
+            _ => BigInt::new(self.negative, sub_digits(&self.digits, &other.digits)),
+        }
+    }
+}
+
+impl Sub for &BigInt {
+    type Output = BigInt;
+
+    fn sub(self, other: Self) -> BigInt {
+        self + &-other
+    }
+}
+
+impl Mul for &BigInt {
+    type Output = BigInt;
+
+    fn mul(self, other: Self) -> BigInt {
+        let mut digits = vec![0u64; self.digits.len() + other.digits.len()];
+
+        for (i, &a) in self.digits.iter().enumerate() {
+            let mut carry = 0;
+
+            for (j, &b) in other.digits.iter().enumerate() {
+                let n = digits[i + j] + a as u64 * b as u64 + carry;
+                digits[i + j] = n % BASE;
+                carry = n / BASE;
+            }
+
+            digits[i + other.digits.len()] += carry;
+        }
+
+        let digits = digits.into_iter().map(|d| d as u32).collect();
+        BigInt::new(self.negative != other.negative, digits)
+    }
+}
+
+impl From<isize> for BigInt {
+    fn from(int: isize) -> Self {
+        let mut n = (int as i128).unsigned_abs();
+        let mut digits = Vec::new();
+
+        while n > 0 {
+            digits.push((n % BASE as u128) as u32);
+            n /= BASE as u128;
+        }
+
+        Self::new(int < 0, digits)
+    }
+}
+
+impl TryFrom<&Value> for BigInt {
+    type Error = ();
+
+    fn try_from(value: &Value) -> Result<Self, Self::Error> {
+        match value {
+            Value::Int(int) => Ok(Self::from(*int)),
+            Value::BigInt(big) => Ok(big.clone()),
+            _ => Err(()),
+        }
+    }
+}
+
+/// Big ints that fit in an isize are turned back into Ints, so each number has only one form.
+impl From<BigInt> for Value {
+    fn from(big: BigInt) -> Self {
+        match big.to_isize() {
+            Some(int) => Value::Int(int),
+            None => Value::BigInt(big),
+        }
+    }
+}
+
+impl Ord for BigInt {
+    fn cmp(&self, other: &Self) -> Ordering {
+        match (self.negative, other.negative) {
+            (false, true) => Ordering::Greater,
+            (true, false) => Ordering::Less,
+            (false, false) => cmp_digits(&self.digits, &other.digits),
+            (true, true) => cmp_digits(&other.digits, &self.digits),
+        }
+    }
+}
+
+impl PartialOrd for BigInt {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl fmt::Display for BigInt {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.negative {
+            write!(f, "-")?;
+        }
+
+        match self.digits.split_last() {
+            Some((last, rest)) => {
+                write!(f, "{}", last)?;
+                for digit in rest.iter().rev() {
+                    write!(f, "{:09}", digit)?;
+                }
+                Ok(())
+            }
+            None => write!(f, "0"),
+        }
+    }
+}
+
+fn cmp_digits(a: &[u32], b: &[u32]) -> Ordering {
+    a.len()
+        .cmp(&b.len())
+        .then_with(|| a.iter().rev().cmp(b.iter().rev()))
+}
+
+fn add_digits(a: &[u32], b: &[u32]) -> Vec<u32> {
+    let mut digits = Vec::new();
+    let mut carry = 0;
+
+    for i in 0..a.len().max(b.len()) {
+        let n = *a.get(i).unwrap_or(&0) as u64 + *b.get(i).unwrap_or(&0) as u64 + carry;
+        digits.push((n % BASE) as u32);
+        carry = n / BASE;
+    }
+
+    if carry > 0 {
+        digits.push(carry as u32);
+    }
+
+    digits
+}
+
+/// `a` must not be smaller than `b`.
+fn sub_digits(a: &[u32], b: &[u32]) -> Vec<u32> {
+    let mut digits = Vec::new();
+    let mut borrow = 0;
+
+    for (i, &digit) in a.iter().enumerate() {
+        let mut n = digit as i64 - *b.get(i).unwrap_or(&0) as i64 - borrow;
+        borrow = 0;
+        if n < 0 {
+            n += BASE as i64;
+            borrow = 1;
+        }
+        digits.push(n as u32);
+    }
+
+    BigInt::new(false, digits).digits
+}
+
+fn mul_digit(a: &[u32], digit: u32) -> Vec<u32> {
+    let mut digits = Vec::new();
+    let mut carry = 0;
+
+    for &n in a.iter() {
+        let n = n as u64 * digit as u64 + carry;
+        digits.push((n % BASE) as u32);
+        carry = n / BASE;
+    }
+
+    if carry > 0 {
+        digits.push(carry as u32);
+    }
+
+    BigInt::new(false, digits).digits
+}
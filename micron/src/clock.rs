@@ -0,0 +1,132 @@
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+/// Where the time functions get the time from, so that the embedders can supply their own.
+pub trait Clock {
+    /// Milliseconds since some fixed point in the past, it never goes backwards.
+    fn monotonic_ms(&mut self) -> u64;
+    /// Seconds since the Unix epoch.
+    fn unix_time(&mut self) -> i64;
+    /// Nanoseconds since the Unix epoch, which `rand:` is seeded with.
+    fn unix_nanos(&mut self) -> i128 {
+        self.unix_time() as i128 * 1_000_000_000
+    }
+
+    fn sleep(&mut self, ms: u64);
+}
+
+/// The clock of the system.
+pub struct SystemClock {
+    start: Instant,
+}
+
+impl Default for SystemClock {
+    fn default() -> Self {
+        Self {
+            start: Instant::now(),
+        }
+    }
+}
+
+impl Clock for SystemClock {
+    fn monotonic_ms(&mut self) -> u64 {
+        self.start.elapsed().as_millis() as u64
+    }
+
+    fn unix_time(&mut self) -> i64 {
+        match SystemTime::now().duration_since(UNIX_EPOCH) {
+            Ok(duration) => duration.as_secs() as i64,
+            Err(err) => -(err.duration().as_secs() as i64),
+        }
+    }
+
+    fn unix_nanos(&mut self) -> i128 {
+        match SystemTime::now().duration_since(UNIX_EPOCH) {
+            Ok(duration) => duration.as_nanos() as i128,
+            Err(err) => -(err.duration().as_nanos() as i128),
+        }
+    }
+
+    fn sleep(&mut self, ms: u64) {
+        thread::sleep(Duration::from_millis(ms));
+    }
+}
+
+/// A clock that starts at the given Unix time and only moves when it's slept on,
+/// which takes no time at all. It makes the runs that depend on time reproducible.
+pub struct FakeClock {
+    start: i64,
+    ms: u64,
+}
+
+impl FakeClock {
+    pub fn new(start: i64) -> Self {
+        Self { start, ms: 0 }
+    }
+}
+
+impl Clock for FakeClock {
+    fn monotonic_ms(&mut self) -> u64 {
+        self.ms
+    }
+
+    fn unix_time(&mut self) -> i64 {
+        self.start + (self.ms / 1000) as i64
+    }
+
+    fn unix_nanos(&mut self) -> i128 {
+        self.start as i128 * 1_000_000_000 + self.ms as i128 * 1_000_000
+    }
+
+    fn sleep(&mut self, ms: u64) {
+        self.ms += ms;
+    }
+}
+
+/// Formats the Unix time (in UTC) replacing `%Y`, `%m`, `%d`, `%H`, `%M` and `%S`
+/// with the year, month, day, hours, minutes and seconds, and `%%` with `%`.
+/// Returns the specifier if it's unknown.
+pub fn format_date(timestamp: i64, format: &str) -> Result<String, String> {
+    let (year, month, day) = civil_from_days(timestamp.div_euclid(86400));
+    let secs = timestamp.rem_euclid(86400);
+
+    let mut s = String::new();
+    let mut chars = format.chars();
+
+    while let Some(ch) = chars.next() {
+        if ch != '%' {
+            s.push(ch);
+            continue;
+        }
+
+        match chars.next() {
+            Some('Y') => s.push_str(&format!("{:04}", year)),
+            Some('m') => s.push_str(&format!("{:02}", month)),
+            Some('d') => s.push_str(&format!("{:02}", day)),
+            Some('H') => s.push_str(&format!("{:02}", secs / 3600)),
+            Some('M') => s.push_str(&format!("{:02}", secs / 60 % 60)),
+            Some('S') => s.push_str(&format!("{:02}", secs % 60)),
+            Some('%') => s.push('%'),
+            Some(ch) => return Err(format!("%{}", ch)),
+            None => return Err("%".to_string()),
+        }
+    }
+
+    Ok(s)
+}
+
+/// The year, month and day of the given number of days since the Unix epoch,
+/// from http://howardhinnant.github.io/date_algorithms.html#civil_from_days
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let z = days + 719_468;
+    let era = if z >= 0 { z } else { z - 146_096 } / 146_097;
+    let doe = z - era * 146_097; // the day of the era, from 0 to 146096
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146_096) / 365; // the year of the era
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100); // the day of the year, starting at March
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + (month <= 2) as i64;
+
+    (year, month, day)
+}
@@ -1,8 +1,18 @@
+#[cfg(feature = "bigint")]
+use crate::bigint::BigInt;
+use crate::clock::{self, Clock};
 use crate::errors::Code;
-use crate::parser::{Expr, Fun, Instr, InstrInfo, Value};
-use std::collections::HashMap;
+use crate::parser::{Expr, Fun, Instr, InstrInfo, Key, Value};
+use crate::random::Rng;
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, HashMap};
+use std::convert::TryFrom;
 use std::fmt;
-use std::process::exit;
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Component, Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::thread;
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct InterpreterError {
@@ -25,6 +35,11 @@ pub enum Error {
     LabelError(String),
     ValueError(Value),
     NoSlotError,
+    OverflowError,
+    ZeroDivisionError,
+    IoError(String),
+    ProcessError(String),
+    PermissionError(Capability),
     Error(String),
 }
 
@@ -35,6 +50,11 @@ impl Error {
             Error::LabelError(..) => 402,
             Error::ValueError(..) => 403,
             Error::NoSlotError => 404,
+            Error::OverflowError => 405,
+            Error::ZeroDivisionError => 406,
+            Error::IoError(..) => 407,
+            Error::ProcessError(..) => 408,
+            Error::PermissionError(..) => 409,
             Error::Error(..) => 400,
         })
     }
@@ -47,6 +67,11 @@ impl fmt::Display for Error {
             Error::LabelError(..) => write!(f, "LabelError"),
             Error::ValueError(..) => write!(f, "ValueError"),
             Error::NoSlotError => write!(f, "NoSlotError"),
+            Error::OverflowError => write!(f, "OverflowError"),
+            Error::ZeroDivisionError => write!(f, "ZeroDivisionError"),
+            Error::IoError(..) => write!(f, "IoError"),
+            Error::ProcessError(..) => write!(f, "ProcessError"),
+            Error::PermissionError(..) => write!(f, "PermissionError"),
             Error::Error(..) => write!(f, "Error"),
         }
     }
@@ -67,46 +92,198 @@ impl ErrorInfo {
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum Signal {
-    Error(ErrorInfo),
-    InterpreterError(InterpreterError),
+    // boxed to keep the `Result`s small, errors are the rare path
+    Error(Box<ErrorInfo>),
+    InterpreterError(Box<InterpreterError>),
     Jump(usize),
     Return(Value),
+    Exit(i32),
 }
 
 type LabelType = HashMap<String, usize>;
 type SlotType = HashMap<isize, Value>;
 type StdOutType = Result<(), ()>;
-type StdInType = Result<String, ()>;
+type StdInType = Result<Option<String>, ()>;
+type KeyCharType = Result<Option<char>, ()>;
+
+/// The callbacks through which the program talks to the outside world,
+/// so that the embedders can supply their own.
+pub struct Host<'a> {
+    pub stdout: &'a mut dyn FnMut(String) -> StdOutType,
+    pub stderr: &'a mut dyn FnMut(String) -> StdOutType,
+    /// Writes out whatever the stdout holds back, called by `flush`.
+    pub flush: &'a mut dyn FnMut() -> StdOutType,
+    /// Reads a line along with its line ending, None at the end of the input.
+    pub stdin: &'a mut dyn FnMut() -> StdInType,
+    /// Reads a single keystroke without waiting for the enter key, None at the end of the input.
+    pub keychar: &'a mut dyn FnMut() -> KeyCharType,
+    /// Used by `ms`, `time`, `sleep:` and `date:`.
+    pub clock: &'a mut dyn Clock,
+}
+
+/// What the side-effecting functions are allowed to do, nothing is allowed by default.
+/// Time isn't one of them, the `Clock` of the `Host` is where the embedders control it.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Permissions {
+    /// The directories the files of which can be read.
+    pub read: Vec<PathBuf>,
+    /// The directories the files of which can be written.
+    pub write: Vec<PathBuf>,
+    /// Whether the environment variables can be read.
+    pub env: bool,
+    /// Whether other programs can be run with `run:`.
+    pub run: bool,
+}
+
+/// The capability a denied function was missing, one for each field of `Permissions`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Capability {
+    Read,
+    Write,
+    Env,
+    Run,
+}
+
+impl Capability {
+    /// The command line flag that grants it.
+    pub fn flag(&self) -> &'static str {
+        match self {
+            Capability::Read => "--allow-read",
+            Capability::Write => "--allow-write",
+            Capability::Env => "--allow-env",
+            Capability::Run => "--allow-run",
+        }
+    }
+}
+
+impl fmt::Display for Capability {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Capability::Read => write!(f, "read"),
+            Capability::Write => write!(f, "write"),
+            Capability::Env => write!(f, "env"),
+            Capability::Run => write!(f, "run"),
+        }
+    }
+}
+
+/// The settings the program is run with.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Options {
+    /// Ints that overflow become big ints instead of raising `OverflowError`.
+    /// This only has an effect when built with the `bigint` feature.
+    pub big_ints: bool,
+    /// The arguments passed to the program after its path.
+    pub args: Vec<String>,
+    pub permissions: Permissions,
+    /// The seed of the numbers `rand:` returns, taken from the `Clock` of the `Host` when None.
+    pub seed: Option<u64>,
+}
+
+/// An error handler installed by `try:`, `index` being the location of its label
+/// and `depth` the routine (`f:`) nesting level it was installed at.
+#[derive(Debug, Clone, PartialEq)]
+struct Handler {
+    index: usize,
+    depth: usize,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct State {
+    slots: SlotType,
+    frames: Vec<SlotType>,
+    handlers: Vec<Handler>,
+    caught: Option<ErrorInfo>,
+    options: Options,
+    rng: Rng,
+}
+
+impl Default for State {
+    fn default() -> Self {
+        Self {
+            slots: HashMap::new(),
+            frames: vec![HashMap::new()], // the frame of the main routine
+            handlers: Vec::new(),
+            caught: None,
+            options: Options::default(),
+            rng: Rng::new(0),
+        }
+    }
+}
+
+impl State {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The routine nesting level, which is the number of local frames.
+    fn depth(&self) -> usize {
+        self.frames.len()
+    }
+
+    /// The local slots of the routine that is currently being executed.
+    fn locals(&mut self) -> &mut SlotType {
+        self.frames.last_mut().unwrap()
+    }
+
+    /// Records the error code to the slot `-1` and remembers the error so that it can be rethrown.
+    fn set_caught(&mut self, error_info: ErrorInfo) {
+        self.slots
+            .insert(-1, Value::Int(error_info.error.error_code().as_isize()));
+        self.caught = Some(error_info);
+    }
+
+    /// Pops the innermost handler if it was installed by the current routine
+    /// and returns the location it jumps to.
+    /// Handlers of the callers are left alone, the error has to reach them first.
+    fn catch(&mut self, error_info: &ErrorInfo) -> Option<usize> {
+        match self.handlers.last() {
+            Some(handler) if handler.depth == self.depth() => {
+                let index = handler.index;
+                self.handlers.pop();
+                self.set_caught(error_info.clone());
+                Some(index)
+            }
+            _ => None,
+        }
+    }
+}
 
 pub fn interpret(
     labels: LabelType,
     instr_infos: Vec<InstrInfo>,
-    stdout: &mut dyn FnMut(String) -> StdOutType,
-    stdin: &mut dyn FnMut() -> StdInType,
-) -> Result<(), InterpreterError> {
-    let mut slots: SlotType = HashMap::new();
-
-    match interpret_instrs(&instr_infos, &labels, &mut slots, 0, stdout, stdin) {
+    host: &mut Host,
+    options: Options,
+) -> Result<i32, Box<InterpreterError>> {
+    let mut state = State::new();
+    state.rng = match options.seed {
+        Some(seed) => Rng::new(seed),
+        None => Rng::new(host.clock.unix_nanos() as u64),
+    };
+    state.options = options;
+
+    match interpret_instrs(&instr_infos, &labels, &mut state, 0, host) {
         Ok(_) => {}
         Err(signal) => match signal {
             Signal::InterpreterError(interpreter_error) => {
                 return Err(interpreter_error);
             }
 
+            Signal::Exit(code) => return Ok(code),
+
             _ => panic!("got {:#?}", signal),
         },
     }
 
-    Ok(())
+    Ok(0)
 }
 
 pub fn interpret_instrs(
     instr_infos: &Vec<InstrInfo>,
     labels: &LabelType,
-    slots: &mut SlotType,
+    state: &mut State,
     mut i: usize,
-    stdout: &mut dyn FnMut(String) -> StdOutType,
-    stdin: &mut dyn FnMut() -> StdInType,
+    host: &mut Host,
 ) -> Result<Value, Signal> {
     let mut instrs = Vec::new();
 
@@ -120,23 +297,32 @@ pub fn interpret_instrs(
 
             Instr::LabelPlaceHolder(..) => {}
 
+            Instr::EndOfFile => break,
+
             Instr::FunCall(fun) => {
-                match interpret_fun_call(fun.clone(), labels, slots, instr_infos, stdout, stdin) {
+                match interpret_fun_call(fun.clone(), labels, state, instr_infos, host) {
                     Ok(_) => {}
                     Err(signal) => match signal {
                         Signal::InterpreterError(interpreter_error) => {
-                            return Err(Signal::InterpreterError(interpreter_error))
+                            match state.catch(&interpreter_error.error_info) {
+                                Some(index) => i = index,
+                                None => return Err(Signal::InterpreterError(interpreter_error)),
+                            }
                         }
 
-                        Signal::Error(error_info) => {
-                            return Err(Signal::InterpreterError(InterpreterError::new(
-                                error_info,
-                                instr_infos[i].clone(),
-                            )))
-                        }
+                        Signal::Error(error_info) => match state.catch(&error_info) {
+                            Some(index) => i = index,
+                            None => {
+                                return Err(Signal::InterpreterError(Box::new(
+                                    InterpreterError::new(*error_info, instr_infos[i].clone()),
+                                )))
+                            }
+                        },
 
                         Signal::Return(value) => return Ok(value),
 
+                        Signal::Exit(code) => return Err(Signal::Exit(code)),
+
                         Signal::Jump(int) => {
                             i = int;
                         }
@@ -153,10 +339,9 @@ pub fn interpret_instrs(
 pub fn interpret_fun_call(
     fun: Box<Fun>,
     labels: &LabelType,
-    slots: &mut SlotType,
+    state: &mut State,
     instr_infos: &Vec<InstrInfo>,
-    stdout: &mut dyn FnMut(String) -> StdOutType,
-    stdin: &mut dyn FnMut() -> StdInType,
+    host: &mut Host,
 ) -> Result<Value, Signal> {
     let clone = *fun.clone();
 
@@ -164,58 +349,106 @@ pub fn interpret_fun_call(
         Fun::Set(expr1, expr2) => {
             let value1 = match expr1 {
                 Expr::Value(v) => v,
-                Expr::FunCall(_fun) => {
-                    interpret_fun_call(_fun, labels, slots, instr_infos, stdout, stdin)?
-                }
+                Expr::FunCall(_fun) => interpret_fun_call(_fun, labels, state, instr_infos, host)?,
             };
 
             let int = match value1 {
                 Value::Int(i) => i,
                 _ => {
-                    return Err(Signal::Error(ErrorInfo::new(
+                    return Err(Signal::Error(Box::new(ErrorInfo::new(
                         Error::TypeError {
                             expected: Value::Int(0),
                             got: value1,
                         },
                         clone,
                         None,
-                    )));
+                    ))));
                 }
             };
 
             let value2 = match expr2 {
                 Expr::Value(v) => v,
-                Expr::FunCall(_fun) => {
-                    interpret_fun_call(_fun, labels, slots, instr_infos, stdout, stdin)?
-                }
+                Expr::FunCall(_fun) => interpret_fun_call(_fun, labels, state, instr_infos, host)?,
             };
 
-            slots.insert(int, value2);
+            state.slots.insert(int, value2);
         }
 
         Fun::Get(expr) => {
             let value = match expr {
                 Expr::Value(v) => v,
-                Expr::FunCall(_fun) => {
-                    interpret_fun_call(_fun, labels, slots, instr_infos, stdout, stdin)?
+                Expr::FunCall(_fun) => interpret_fun_call(_fun, labels, state, instr_infos, host)?,
+            };
+
+            let int = match value {
+                Value::Int(i) => i,
+                _ => {
+                    return Err(Signal::Error(Box::new(ErrorInfo::new(
+                        Error::TypeError {
+                            expected: Value::Int(0),
+                            got: value,
+                        },
+                        clone,
+                        None,
+                    ))));
+                }
+            };
+
+            match state.slots.get(&int) {
+                Some(v) => return Ok(v.clone()),
+                None => return Ok(Value::None),
+            }
+        }
+
+        Fun::LocalSet(expr1, expr2) => {
+            let value1 = match expr1 {
+                Expr::Value(v) => v,
+                Expr::FunCall(_fun) => interpret_fun_call(_fun, labels, state, instr_infos, host)?,
+            };
+
+            let int = match value1 {
+                Value::Int(i) => i,
+                _ => {
+                    return Err(Signal::Error(Box::new(ErrorInfo::new(
+                        Error::TypeError {
+                            expected: Value::Int(0),
+                            got: value1,
+                        },
+                        clone,
+                        None,
+                    ))));
                 }
             };
 
+            let value2 = match expr2 {
+                Expr::Value(v) => v,
+                Expr::FunCall(_fun) => interpret_fun_call(_fun, labels, state, instr_infos, host)?,
+            };
+
+            state.locals().insert(int, value2);
+        }
+
+        Fun::LocalGet(expr) => {
+            let value = match expr {
+                Expr::Value(v) => v,
+                Expr::FunCall(_fun) => interpret_fun_call(_fun, labels, state, instr_infos, host)?,
+            };
+
             let int = match value {
                 Value::Int(i) => i,
                 _ => {
-                    return Err(Signal::Error(ErrorInfo::new(
+                    return Err(Signal::Error(Box::new(ErrorInfo::new(
                         Error::TypeError {
                             expected: Value::Int(0),
                             got: value,
                         },
                         clone,
                         None,
-                    )));
+                    ))));
                 }
             };
 
-            match slots.get(&int) {
+            match state.locals().get(&int) {
                 Some(v) => return Ok(v.clone()),
                 None => return Ok(Value::None),
             }
@@ -224,70 +457,85 @@ pub fn interpret_fun_call(
         Fun::Jump(expr) => {
             let value = match expr {
                 Expr::Value(v) => v,
-                Expr::FunCall(_fun) => {
-                    interpret_fun_call(_fun, labels, slots, instr_infos, stdout, stdin)?
-                }
+                Expr::FunCall(_fun) => interpret_fun_call(_fun, labels, state, instr_infos, host)?,
             };
 
             let string = match value {
                 Value::Str(s) => s,
                 _ => {
-                    return Err(Signal::Error(ErrorInfo::new(
+                    return Err(Signal::Error(Box::new(ErrorInfo::new(
                         Error::TypeError {
                             expected: Value::Str(String::from("")),
                             got: value,
                         },
                         clone,
                         None,
-                    )));
+                    ))));
                 }
             };
 
             match labels.get(&string) {
                 Some(i) => return Err(Signal::Jump(*i)),
                 None => {
-                    return Err(Signal::Error(ErrorInfo::new(
+                    return Err(Signal::Error(Box::new(ErrorInfo::new(
                         Error::LabelError(string.to_string()),
                         clone,
                         None,
-                    )));
+                    ))));
                 }
             }
         }
 
-        Fun::FunJump(expr) => {
+        Fun::FunJump(expr, arg_exprs) => {
             let value = match expr {
                 Expr::Value(v) => v,
-                Expr::FunCall(_fun) => {
-                    interpret_fun_call(_fun, labels, slots, instr_infos, stdout, stdin)?
-                }
+                Expr::FunCall(_fun) => interpret_fun_call(_fun, labels, state, instr_infos, host)?,
             };
 
             let string = match value {
                 Value::Str(s) => s,
                 _ => {
-                    return Err(Signal::Error(ErrorInfo::new(
+                    return Err(Signal::Error(Box::new(ErrorInfo::new(
                         Error::TypeError {
                             expected: Value::Str(String::from("")),
                             got: value,
                         },
                         clone,
                         None,
-                    )));
+                    ))));
                 }
             };
 
             match labels.get(&string) {
                 Some(i) => {
-                    let result = interpret_instrs(instr_infos, labels, slots, *i, stdout, stdin)?;
-                    return Ok(result);
+                    // the arguments are evaluated in the frame of the caller
+                    let mut frame = HashMap::new();
+                    for (n, arg_expr) in arg_exprs.into_iter().enumerate() {
+                        let arg = match arg_expr {
+                            Expr::Value(v) => v,
+                            Expr::FunCall(_fun) => {
+                                interpret_fun_call(_fun, labels, state, instr_infos, host)?
+                            }
+                        };
+                        frame.insert(n as isize, arg);
+                    }
+
+                    state.frames.push(frame);
+                    let result = interpret_instrs(instr_infos, labels, state, *i, host);
+                    state.frames.pop();
+
+                    // handlers installed by the routine do not outlive it
+                    let depth = state.depth();
+                    state.handlers.retain(|handler| handler.depth <= depth);
+
+                    return result;
                 }
                 None => {
-                    return Err(Signal::Error(ErrorInfo::new(
+                    return Err(Signal::Error(Box::new(ErrorInfo::new(
                         Error::LabelError(string.to_string()),
                         clone,
                         None,
-                    )));
+                    ))));
                 }
             }
         }
@@ -295,32 +543,116 @@ pub fn interpret_fun_call(
         Fun::Add(expr1, expr2) => {
             let value1 = match expr1 {
                 Expr::Value(v) => v,
-                Expr::FunCall(_fun) => {
-                    interpret_fun_call(_fun, labels, slots, instr_infos, stdout, stdin)?
-                }
+                Expr::FunCall(_fun) => interpret_fun_call(_fun, labels, state, instr_infos, host)?,
             };
 
             let value2 = match expr2 {
                 Expr::Value(v) => v,
-                Expr::FunCall(_fun) => {
-                    interpret_fun_call(_fun, labels, slots, instr_infos, stdout, stdin)?
-                }
+                Expr::FunCall(_fun) => interpret_fun_call(_fun, labels, state, instr_infos, host)?,
             };
 
             match (&value1, &value2) {
-                (Value::Int(int1), Value::Int(int2)) => return Ok(Value::Int(int1 + int2)),
+                (Value::Int(int1), Value::Int(int2)) => match int1.checked_add(*int2) {
+                    Some(int) => return Ok(Value::Int(int)),
+                    None => return overflowed(clone, value1, value2, state.options.big_ints),
+                },
+                #[cfg(feature = "bigint")]
+                (Value::BigInt(_), _) | (_, Value::BigInt(_))
+                    if is_int(&value1) && is_int(&value2) =>
+                {
+                    return big_arithmetic(clone, value1, value2)
+                }
+                (Value::Float(float1), Value::Float(float2)) => {
+                    return float_result(clone, float1 + float2)
+                }
+                (Value::Int(_), Value::Float(_)) | (Value::Float(_), Value::Int(_)) => {
+                    return Err(mixed_numbers(clone, value1, value2))
+                }
                 (Value::Str(str1), Value::Str(str2)) => {
                     return Ok(Value::Str(str1.to_owned() + str2))
                 }
+                (Value::List(list1), Value::List(list2)) => {
+                    return Ok(Value::List([&list1[..], &list2[..]].concat()))
+                }
                 _ => {
-                    return Err(Signal::Error(ErrorInfo::new(
+                    return Err(Signal::Error(Box::new(ErrorInfo::new(
                         Error::Error(format!(
                             "You are trying to add {} and {} which is invalid",
                             &value1, &value2
                         )),
                         clone,
                         None,
-                    )))
+                    ))))
+                }
+            }
+        }
+
+        Fun::Sub(expr1, expr2)
+        | Fun::Mul(expr1, expr2)
+        | Fun::Div(expr1, expr2)
+        | Fun::Mod(expr1, expr2)
+        | Fun::Pow(expr1, expr2)
+        | Fun::Min(expr1, expr2)
+        | Fun::Max(expr1, expr2) => {
+            let value1 = interpret_expr(expr1, labels, state, instr_infos, host)?;
+            let value2 = interpret_expr(expr2, labels, state, instr_infos, host)?;
+
+            return arithmetic(clone, value1, value2, state.options.big_ints);
+        }
+
+        Fun::Random(expr1, expr2) => {
+            let low = to_int(
+                interpret_expr(expr1, labels, state, instr_infos, host)?,
+                &clone,
+            )?;
+            let high = to_int(
+                interpret_expr(expr2, labels, state, instr_infos, host)?,
+                &clone,
+            )?;
+
+            match state.rng.range(low, high) {
+                Some(int) => return Ok(Value::Int(int)),
+                None => {
+                    return Err(Signal::Error(Box::new(ErrorInfo::new(
+                        Error::ValueError(Value::Int(high)),
+                        clone,
+                        Some(format!("The range ends before it starts at {}", low)),
+                    ))))
+                }
+            }
+        }
+
+        Fun::Abs(expr) => {
+            let value = interpret_expr(expr, labels, state, instr_infos, host)?;
+
+            match value {
+                Value::Int(int) => match int.checked_abs() {
+                    Some(int) => return Ok(Value::Int(int)),
+                    #[cfg(feature = "bigint")]
+                    None if state.options.big_ints => return Ok(BigInt::from(int).abs().into()),
+                    None => {
+                        return Err(Signal::Error(Box::new(ErrorInfo::new(
+                            Error::OverflowError,
+                            clone,
+                            Some(format!(
+                                "The absolute value of {} is too large for an Int",
+                                int
+                            )),
+                        ))))
+                    }
+                },
+                Value::Float(float) => return Ok(Value::Float(float.abs())),
+                #[cfg(feature = "bigint")]
+                Value::BigInt(big) => return Ok(Value::BigInt(big.abs())),
+                _ => {
+                    return Err(Signal::Error(Box::new(ErrorInfo::new(
+                        Error::TypeError {
+                            expected: Value::Int(0),
+                            got: value,
+                        },
+                        clone,
+                        None,
+                    ))))
                 }
             }
         }
@@ -328,57 +660,47 @@ pub fn interpret_fun_call(
         Fun::CatchError(expr1, expr2) => {
             let value1 = match expr1 {
                 Expr::Value(v) => v,
-                Expr::FunCall(_fun) => {
-                    interpret_fun_call(_fun, labels, slots, instr_infos, stdout, stdin)?
-                }
+                Expr::FunCall(_fun) => interpret_fun_call(_fun, labels, state, instr_infos, host)?,
             };
 
             let string = match value1 {
                 Value::Str(s) => s,
                 _ => {
-                    return Err(Signal::Error(ErrorInfo::new(
+                    return Err(Signal::Error(Box::new(ErrorInfo::new(
                         Error::TypeError {
                             expected: Value::Str(String::from("")),
                             got: value1,
                         },
                         clone,
                         None,
-                    )));
+                    ))));
                 }
             };
 
             let i = *match labels.get(&string) {
                 Some(i) => i,
                 None => {
-                    return Err(Signal::Error(ErrorInfo::new(
+                    return Err(Signal::Error(Box::new(ErrorInfo::new(
                         Error::LabelError(string.to_string()),
                         clone,
                         None,
-                    )));
+                    ))));
                 }
             };
 
             let value2 = match expr2 {
                 Expr::Value(v) => v,
                 Expr::FunCall(_fun) => {
-                    match interpret_fun_call(_fun, labels, slots, instr_infos, stdout, stdin) {
+                    match interpret_fun_call(_fun, labels, state, instr_infos, host) {
                         Ok(v) => v,
                         Err(signal) => match signal {
                             Signal::Error(error_info) => {
-                                slots.insert(
-                                    -1,
-                                    Value::Int(error_info.error.error_code().as_isize()),
-                                );
+                                state.set_caught(*error_info);
                                 return Err(Signal::Jump(i));
                             }
 
                             Signal::InterpreterError(interpreter_error) => {
-                                slots.insert(
-                                    -1,
-                                    Value::Int(
-                                        interpreter_error.error_info.error.error_code().as_isize(),
-                                    ),
-                                );
+                                state.set_caught(interpreter_error.error_info);
                                 return Err(Signal::Jump(i));
                             }
 
@@ -394,73 +716,84 @@ pub fn interpret_fun_call(
         Fun::ThrowError(expr) => {
             let value = match expr {
                 Expr::Value(v) => v,
-                Expr::FunCall(_fun) => {
-                    interpret_fun_call(_fun, labels, slots, instr_infos, stdout, stdin)?
-                }
+                Expr::FunCall(_fun) => interpret_fun_call(_fun, labels, state, instr_infos, host)?,
             };
 
             let string = match value {
                 Value::Str(s) => s.to_string(),
                 Value::Int(int) => int.to_string(),
+                value @ Value::Float(_) | value @ Value::List(_) | value @ Value::Map(_) => {
+                    value.text()
+                }
+                #[cfg(feature = "bigint")]
+                value @ Value::BigInt(_) => value.text(),
                 Value::None => "".to_string(),
             };
 
-            return Err(Signal::Error(ErrorInfo::new(
+            return Err(Signal::Error(Box::new(ErrorInfo::new(
                 Error::Error(string),
                 clone.clone(),
                 Some(format!("This is an error raise by function `{}`", &clone)),
-            )));
+            ))));
         }
 
-        Fun::Print(expr) => {
+        Fun::Print(expr) | Fun::PrintErr(expr) => {
             let value = match expr {
                 Expr::Value(v) => v,
-                Expr::FunCall(_fun) => {
-                    interpret_fun_call(_fun, labels, slots, instr_infos, stdout, stdin)?
-                }
+                Expr::FunCall(_fun) => interpret_fun_call(_fun, labels, state, instr_infos, host)?,
+            };
+
+            let out = match clone {
+                Fun::PrintErr(..) => &mut host.stderr,
+                _ => &mut host.stdout,
             };
 
             let _ = match value {
-                Value::Str(s) => stdout(format!("{}\n", s)),
-                Value::Int(int) => stdout(format!("{}\n", int)),
-                Value::None => stdout(format!("None\n")),
+                Value::Str(s) => out(format!("{}\n", s)),
+                Value::Int(int) => out(format!("{}\n", int)),
+                value @ Value::Float(_) | value @ Value::List(_) | value @ Value::Map(_) => {
+                    out(format!("{}\n", value.text()))
+                }
+                #[cfg(feature = "bigint")]
+                value @ Value::BigInt(_) => out(format!("{}\n", value.text())),
+                Value::None => out(format!("None\n")),
             };
         }
 
-        Fun::Write(expr) => {
+        Fun::Write(expr) | Fun::WriteErr(expr) => {
             let value = match expr {
                 Expr::Value(v) => v,
-                Expr::FunCall(_fun) => {
-                    interpret_fun_call(_fun, labels, slots, instr_infos, stdout, stdin)?
-                }
+                Expr::FunCall(_fun) => interpret_fun_call(_fun, labels, state, instr_infos, host)?,
+            };
+
+            let out = match clone {
+                Fun::WriteErr(..) => &mut host.stderr,
+                _ => &mut host.stdout,
             };
 
             let _ = match value {
-                Value::Str(s) => stdout(format!("{}", s)),
-                Value::Int(int) => stdout(format!("{}", int)),
-                Value::None => stdout(format!("None")),
+                Value::Str(s) => out(format!("{}", s)),
+                Value::Int(int) => out(format!("{}", int)),
+                value @ Value::Float(_) | value @ Value::List(_) | value @ Value::Map(_) => {
+                    out(value.text())
+                }
+                #[cfg(feature = "bigint")]
+                value @ Value::BigInt(_) => out(value.text()),
+                Value::None => out(format!("None")),
             };
         }
 
         Fun::If(expr1, expr2) => {
             let value1 = match expr1 {
                 Expr::Value(v) => v,
-                Expr::FunCall(_fun) => {
-                    interpret_fun_call(_fun, labels, slots, instr_infos, stdout, stdin)?
-                }
-            };
-
-            let condit = match value1 {
-                Value::Str(s) => s != "",
-                Value::Int(int) => int != 0,
-                Value::None => false,
+                Expr::FunCall(_fun) => interpret_fun_call(_fun, labels, state, instr_infos, host)?,
             };
 
-            if condit {
+            if value1.is_truthy() {
                 let value = match expr2 {
                     Expr::Value(v) => v,
                     Expr::FunCall(_fun) => {
-                        interpret_fun_call(_fun, labels, slots, instr_infos, stdout, stdin)?
+                        interpret_fun_call(_fun, labels, state, instr_infos, host)?
                     }
                 };
 
@@ -468,19 +801,24 @@ pub fn interpret_fun_call(
             }
         }
 
+        Fun::IfElse(expr1, expr2, expr3) => {
+            let value1 = interpret_expr(expr1, labels, state, instr_infos, host)?;
+
+            // only the chosen value is evaluated
+            let expr = if value1.is_truthy() { expr2 } else { expr3 };
+
+            return interpret_expr(expr, labels, state, instr_infos, host);
+        }
+
         Fun::Equal(expr1, expr2) => {
             let value1 = match expr1 {
                 Expr::Value(v) => v,
-                Expr::FunCall(_fun) => {
-                    interpret_fun_call(_fun, labels, slots, instr_infos, stdout, stdin)?
-                }
+                Expr::FunCall(_fun) => interpret_fun_call(_fun, labels, state, instr_infos, host)?,
             };
 
             let value2 = match expr2 {
                 Expr::Value(v) => v,
-                Expr::FunCall(_fun) => {
-                    interpret_fun_call(_fun, labels, slots, instr_infos, stdout, stdin)?
-                }
+                Expr::FunCall(_fun) => interpret_fun_call(_fun, labels, state, instr_infos, host)?,
             };
 
             match (&value1, &value2) {
@@ -498,32 +836,104 @@ pub fn interpret_fun_call(
                         return Ok(Value::Int(0));
                     }
                 }
+                (Value::Float(float1), Value::Float(float2)) => {
+                    return Ok(Value::Int((float1 == float2) as isize))
+                }
+                #[cfg(feature = "bigint")]
+                (Value::BigInt(_), _) | (_, Value::BigInt(_))
+                    if is_int(&value1) && is_int(&value2) =>
+                {
+                    return Ok(Value::Int((value1 == value2) as isize))
+                }
+                (Value::Int(_), Value::Float(_)) | (Value::Float(_), Value::Int(_)) => {
+                    return Err(mixed_numbers(clone, value1, value2))
+                }
+                (Value::List(list1), Value::List(list2)) => {
+                    return Ok(Value::Int((list1 == list2) as isize))
+                }
+                (Value::Map(map1), Value::Map(map2)) => {
+                    return Ok(Value::Int((map1 == map2) as isize))
+                }
                 _ => {
-                    return Err(Signal::Error(ErrorInfo::new(
+                    return Err(Signal::Error(Box::new(ErrorInfo::new(
                         Error::Error(format!(
                             "You are trying to compare {} and {} which is invalid",
                             &value1, &value2
                         )),
                         clone,
                         None,
-                    )));
+                    ))));
                 }
             }
         }
 
+        Fun::Less(expr1, expr2) | Fun::Greater(expr1, expr2) => {
+            let value1 = interpret_expr(expr1, labels, state, instr_infos, host)?;
+            let value2 = interpret_expr(expr2, labels, state, instr_infos, host)?;
+
+            // NaN is neither less nor greater than anything
+            let ordering = match (&value1, &value2) {
+                (Value::Int(int1), Value::Int(int2)) => Some(int1.cmp(int2)),
+                (Value::Float(float1), Value::Float(float2)) => float1.partial_cmp(float2),
+                #[cfg(feature = "bigint")]
+                (Value::BigInt(_), _) | (_, Value::BigInt(_))
+                    if is_int(&value1) && is_int(&value2) =>
+                {
+                    let big1 = BigInt::try_from(&value1).unwrap();
+                    Some(big1.cmp(&BigInt::try_from(&value2).unwrap()))
+                }
+                (Value::Str(str1), Value::Str(str2)) => Some(str1.cmp(str2)),
+                (Value::Int(_), Value::Float(_)) | (Value::Float(_), Value::Int(_)) => {
+                    return Err(mixed_numbers(clone, value1, value2))
+                }
+                _ => {
+                    return Err(Signal::Error(Box::new(ErrorInfo::new(
+                        Error::Error(format!(
+                            "You are trying to compare {} and {} which is invalid",
+                            &value1, &value2
+                        )),
+                        clone,
+                        None,
+                    ))));
+                }
+            };
+
+            let result = match clone {
+                Fun::Less(..) => ordering == Some(Ordering::Less),
+                _ => ordering == Some(Ordering::Greater),
+            };
+
+            return Ok(Value::Int(result as isize));
+        }
+
+        // the second value is only evaluated when the first one doesn't decide the result
+        Fun::And(expr1, expr2) | Fun::Or(expr1, expr2) => {
+            let value1 = interpret_expr(expr1, labels, state, instr_infos, host)?;
+
+            let result = match (&clone, value1.is_truthy()) {
+                (Fun::And(..), false) => false,
+                (Fun::Or(..), true) => true,
+                _ => interpret_expr(expr2, labels, state, instr_infos, host)?.is_truthy(),
+            };
+
+            return Ok(Value::Int(result as isize));
+        }
+
+        Fun::Not(expr) => {
+            let value = interpret_expr(expr, labels, state, instr_infos, host)?;
+
+            return Ok(Value::Int(!value.is_truthy() as isize));
+        }
+
         Fun::Extract(expr1, expr2) => {
             let value1 = match expr1 {
                 Expr::Value(v) => v,
-                Expr::FunCall(_fun) => {
-                    interpret_fun_call(_fun, labels, slots, instr_infos, stdout, stdin)?
-                }
+                Expr::FunCall(_fun) => interpret_fun_call(_fun, labels, state, instr_infos, host)?,
             };
 
             let value2 = match expr2 {
                 Expr::Value(v) => v,
-                Expr::FunCall(_fun) => {
-                    interpret_fun_call(_fun, labels, slots, instr_infos, stdout, stdin)?
-                }
+                Expr::FunCall(_fun) => interpret_fun_call(_fun, labels, state, instr_infos, host)?,
             };
 
             match (&value1, &value2) {
@@ -535,93 +945,593 @@ pub fn interpret_fun_call(
                     }
                 }
                 _ => {
-                    return Err(Signal::Error(ErrorInfo::new(
+                    return Err(Signal::Error(Box::new(ErrorInfo::new(
                         Error::Error(format!(
                             "You are trying to extract from {} using index value {} which is invalid",
                             &value1, &value2
                         )),
                         clone,
                         None,
-                    )));
+                    ))));
                 }
             }
         }
 
-        Fun::Text(expr) => {
-            let value = match expr {
-                Expr::Value(v) => v,
-                Expr::FunCall(_fun) => {
-                    interpret_fun_call(_fun, labels, slots, instr_infos, stdout, stdin)?
+        Fun::Len(expr) => {
+            let value = interpret_expr(expr, labels, state, instr_infos, host)?;
+
+            match value {
+                Value::Str(s) => return Ok(Value::Int(s.chars().count() as isize)),
+                Value::List(list) => return Ok(Value::Int(list.len() as isize)),
+                Value::Map(map) => return Ok(Value::Int(map.len() as isize)),
+                _ => return Err(type_error(Value::Str(String::from("")), value, clone)),
+            }
+        }
+
+        Fun::Slice(expr1, expr2, expr3) => {
+            let value1 = interpret_expr(expr1, labels, state, instr_infos, host)?;
+            let start = to_int(
+                interpret_expr(expr2, labels, state, instr_infos, host)?,
+                &clone,
+            )?;
+            let end = to_int(
+                interpret_expr(expr3, labels, state, instr_infos, host)?,
+                &clone,
+            )?;
+
+            match value1 {
+                Value::Str(s) => {
+                    let chars = s.chars().collect::<Vec<char>>();
+                    let (start, end) = slice_range(chars.len(), start, end);
+
+                    return Ok(Value::Str(chars[start..end].iter().collect()));
                 }
-            };
+                Value::List(list) => {
+                    let (start, end) = slice_range(list.len(), start, end);
 
-            match &value {
-                Value::Int(int) => return Ok(Value::Str(int.to_string())),
-                _ => {
-                    return Err(Signal::Error(ErrorInfo::new(
-                        Error::TypeError {
-                            expected: Value::Int(0),
-                            got: value.clone(),
-                        },
-                        clone,
-                        None,
-                    )))
+                    return Ok(Value::List(list[start..end].to_vec()));
                 }
+                _ => return Err(type_error(Value::Str(String::from("")), value1, clone)),
             }
         }
 
-        Fun::Number(expr) => {
-            let value = match expr {
-                Expr::Value(v) => v,
-                Expr::FunCall(_fun) => {
-                    interpret_fun_call(_fun, labels, slots, instr_infos, stdout, stdin)?
-                }
-            };
+        Fun::List => return Ok(Value::List(Vec::new())),
 
-            match &value {
-                Value::Str(s) => match s.parse::<isize>() {
-                    Ok(int) => return Ok(Value::Int(int)),
-                    Err(_) => {
-                        return Err(Signal::Error(ErrorInfo::new(
-                            Error::ValueError(value.clone()),
-                            clone,
-                            Some(format!("Cannot convert {} to an Int", value)),
-                        )))
-                    }
-                },
+        Fun::Push(expr1, expr2) | Fun::LocalPush(expr1, expr2) => {
+            let place = place_of(expr1, labels, state, instr_infos, host, &clone)?;
+            let value = interpret_expr(expr2, labels, state, instr_infos, host)?;
 
-                _ => {
-                    return Err(Signal::Error(ErrorInfo::new(
-                        Error::TypeError {
-                            expected: Value::Int(0),
-                            got: value.clone(),
-                        },
+            slot_list(state, place, &clone)?.push(value);
+        }
+
+        Fun::Pop(expr) | Fun::LocalPop(expr) => {
+            let place = place_of(expr, labels, state, instr_infos, host, &clone)?;
+
+            match slot_list(state, place, &clone)?.pop() {
+                Some(value) => return Ok(value),
+                None => {
+                    return Err(Signal::Error(Box::new(ErrorInfo::new(
+                        Error::ValueError(Value::List(Vec::new())),
                         clone,
-                        None,
-                    )))
+                        Some(format!("Cannot pop from the empty List of {}", place)),
+                    ))))
                 }
             }
         }
 
-        Fun::Convert(expr) => {
-            let value = match expr {
-                Expr::Value(v) => v,
-                Expr::FunCall(_fun) => {
-                    interpret_fun_call(_fun, labels, slots, instr_infos, stdout, stdin)?
-                }
-            };
+        Fun::At(expr1, expr2) => {
+            let value1 = interpret_expr(expr1, labels, state, instr_infos, host)?;
+            let value2 = interpret_expr(expr2, labels, state, instr_infos, host)?;
 
-            match value {
-                Value::Str(ref s) => {
-                    if s.len() != 1 {
-                        return Err(Signal::Error(ErrorInfo::new(
-                            Error::ValueError(value.clone()),
+            match value1 {
+                Value::List(list) => match list_index(list.len(), to_int(value2, &clone)?) {
+                    Some(n) => return Ok(list[n].clone()),
+                    None => return Ok(Value::None),
+                },
+                Value::Map(map) => match map.get(&to_key(value2, &clone)?) {
+                    Some(value) => return Ok(value.clone()),
+                    None => return Ok(Value::None),
+                },
+                _ => return Err(type_error(Value::List(Vec::new()), value1, clone)),
+            }
+        }
+
+        Fun::Put(expr1, expr2, expr3) | Fun::LocalPut(expr1, expr2, expr3) => {
+            let place = place_of(expr1, labels, state, instr_infos, host, &clone)?;
+            let value2 = interpret_expr(expr2, labels, state, instr_infos, host)?;
+            let value = interpret_expr(expr3, labels, state, instr_infos, host)?;
+
+            match place_value(state, place) {
+                Some(Value::List(list)) => {
+                    let index = to_int(value2, &clone)?;
+
+                    match list_index(list.len(), index) {
+                        Some(n) => list[n] = value,
+                        None => {
+                            let len = list.len();
+                            return Err(Signal::Error(Box::new(ErrorInfo::new(
+                                Error::ValueError(Value::Int(index)),
+                                clone,
+                                Some(format!(
+                                    "The index is out of bounds of a List of {} items",
+                                    len
+                                )),
+                            ))));
+                        }
+                    }
+                }
+                Some(Value::Map(map)) => {
+                    map.insert(to_key(value2, &clone)?, value);
+                }
+                other => {
+                    let got = other.cloned().unwrap_or(Value::None);
+                    return Err(type_error(Value::List(Vec::new()), got, clone));
+                }
+            }
+        }
+
+        Fun::Map => return Ok(Value::Map(BTreeMap::new())),
+
+        Fun::Del(expr1, expr2) | Fun::LocalDel(expr1, expr2) => {
+            let place = place_of(expr1, labels, state, instr_infos, host, &clone)?;
+            let key = to_key(
+                interpret_expr(expr2, labels, state, instr_infos, host)?,
+                &clone,
+            )?;
+
+            match place_value(state, place) {
+                Some(Value::Map(map)) => return Ok(map.remove(&key).unwrap_or(Value::None)),
+                other => {
+                    let got = other.cloned().unwrap_or(Value::None);
+                    return Err(type_error(Value::Map(BTreeMap::new()), got, clone));
+                }
+            }
+        }
+
+        Fun::Has(expr1, expr2) => {
+            let value1 = interpret_expr(expr1, labels, state, instr_infos, host)?;
+            let key = to_key(
+                interpret_expr(expr2, labels, state, instr_infos, host)?,
+                &clone,
+            )?;
+
+            match value1 {
+                Value::Map(map) => return Ok(Value::Int(map.contains_key(&key) as isize)),
+                _ => return Err(type_error(Value::Map(BTreeMap::new()), value1, clone)),
+            }
+        }
+
+        Fun::Keys(expr) => {
+            let value = interpret_expr(expr, labels, state, instr_infos, host)?;
+
+            match value {
+                Value::Map(map) => {
+                    return Ok(Value::List(map.into_keys().map(Value::from).collect()))
+                }
+                _ => return Err(type_error(Value::Map(BTreeMap::new()), value, clone)),
+            }
+        }
+
+        Fun::ToFloat(expr) => {
+            let value = interpret_expr(expr, labels, state, instr_infos, host)?;
+
+            match value {
+                Value::Int(int) => return Ok(Value::Float(int as f64)),
+                #[cfg(feature = "bigint")]
+                Value::BigInt(_) => return float_result(clone, value.text().parse().unwrap()),
+                Value::Float(_) => return Ok(value),
+                _ => return Err(type_error(Value::Int(0), value, clone)),
+            }
+        }
+
+        // truncates towards zero
+        Fun::ToInt(expr) => {
+            let value = interpret_expr(expr, labels, state, instr_infos, host)?;
+
+            match value {
+                Value::Int(_) => return Ok(value),
+                Value::Float(float) => {
+                    let int = float.trunc();
+
+                    if int.is_nan() || int < isize::MIN as f64 || int >= isize::MAX as f64 {
+                        return Err(Signal::Error(Box::new(ErrorInfo::new(
+                            Error::ValueError(value),
+                            clone,
+                            Some(format!("{:?} doesn't fit in an Int", float)),
+                        ))));
+                    }
+
+                    return Ok(Value::Int(int as isize));
+                }
+                _ => return Err(type_error(Value::Float(0.0), value, clone)),
+            }
+        }
+
+        Fun::BitAnd(expr1, expr2)
+        | Fun::BitOr(expr1, expr2)
+        | Fun::BitXor(expr1, expr2)
+        | Fun::Shl(expr1, expr2)
+        | Fun::Shr(expr1, expr2) => {
+            let int1 = to_int(
+                interpret_expr(expr1, labels, state, instr_infos, host)?,
+                &clone,
+            )?;
+            let int2 = to_int(
+                interpret_expr(expr2, labels, state, instr_infos, host)?,
+                &clone,
+            )?;
+
+            return bitwise(clone, int1, int2);
+        }
+
+        Fun::BitNot(expr) => {
+            let int = to_int(
+                interpret_expr(expr, labels, state, instr_infos, host)?,
+                &clone,
+            )?;
+
+            return Ok(Value::Int(!int));
+        }
+
+        Fun::TypeOf(expr) => {
+            let value = interpret_expr(expr, labels, state, instr_infos, host)?;
+
+            return Ok(Value::Str(value.type_name().to_string()));
+        }
+
+        Fun::IsNone(expr) => {
+            let value = interpret_expr(expr, labels, state, instr_infos, host)?;
+
+            return Ok(Value::Int((value == Value::None) as isize));
+        }
+
+        // the same rendering as the one of the error messages
+        Fun::Repr(expr) => {
+            let value = interpret_expr(expr, labels, state, instr_infos, host)?;
+
+            return Ok(Value::Str(value.to_string()));
+        }
+
+        Fun::ReadFile(expr) => {
+            let path = to_str(
+                interpret_expr(expr, labels, state, instr_infos, host)?,
+                &clone,
+            )?;
+            check_access(&clone, &path, &state.options.permissions, Capability::Read)?;
+
+            match fs::read_to_string(&path) {
+                Ok(s) => return Ok(Value::Str(s)),
+                Err(err) => return Err(io_error(clone, path, err)),
+            }
+        }
+
+        Fun::WriteFile(expr1, expr2) | Fun::AppendFile(expr1, expr2) => {
+            let path = to_str(
+                interpret_expr(expr1, labels, state, instr_infos, host)?,
+                &clone,
+            )?;
+            let value = interpret_expr(expr2, labels, state, instr_infos, host)?;
+            check_access(&clone, &path, &state.options.permissions, Capability::Write)?;
+
+            let file = fs::OpenOptions::new()
+                .create(true)
+                .write(true)
+                .append(matches!(clone, Fun::AppendFile(..)))
+                .truncate(matches!(clone, Fun::WriteFile(..)))
+                .open(&path);
+
+            // written the way `w:` writes it
+            if let Err(err) = file.and_then(|mut file| file.write_all(value.text().as_bytes())) {
+                return Err(io_error(clone, path, err));
+            }
+        }
+
+        Fun::FileExists(expr) => {
+            let path = to_str(
+                interpret_expr(expr, labels, state, instr_infos, host)?,
+                &clone,
+            )?;
+            check_access(&clone, &path, &state.options.permissions, Capability::Read)?;
+
+            return Ok(Value::Int(Path::new(&path).exists() as isize));
+        }
+
+        Fun::ListDir(expr) => {
+            let path = to_str(
+                interpret_expr(expr, labels, state, instr_infos, host)?,
+                &clone,
+            )?;
+            check_access(&clone, &path, &state.options.permissions, Capability::Read)?;
+
+            let names = fs::read_dir(&path).and_then(|entries| {
+                entries
+                    .map(|entry| entry.map(|entry| entry.file_name().to_string_lossy().to_string()))
+                    .collect::<io::Result<Vec<String>>>()
+            });
+
+            match names {
+                Ok(mut names) => {
+                    names.sort();
+                    return Ok(Value::List(names.into_iter().map(Value::Str).collect()));
+                }
+                Err(err) => return Err(io_error(clone, path, err)),
+            }
+        }
+
+        Fun::ArgCount => return Ok(Value::Int(state.options.args.len() as isize)),
+
+        Fun::Arg(expr) => {
+            let index = to_int(
+                interpret_expr(expr, labels, state, instr_infos, host)?,
+                &clone,
+            )?;
+
+            match list_index(state.options.args.len(), index) {
+                Some(n) => return Ok(Value::Str(state.options.args[n].clone())),
+                None => return Ok(Value::None),
+            }
+        }
+
+        Fun::Env(expr) => {
+            let name = to_str(
+                interpret_expr(expr, labels, state, instr_infos, host)?,
+                &clone,
+            )?;
+
+            check_permission(&clone, &state.options.permissions, Capability::Env)?;
+
+            match std::env::var(&name) {
+                Ok(value) => return Ok(Value::Str(value)),
+                Err(_) => return Ok(Value::None),
+            }
+        }
+
+        Fun::Run(expr1, expr2, expr3) => {
+            let program = to_str(
+                interpret_expr(expr1, labels, state, instr_infos, host)?,
+                &clone,
+            )?;
+            let args = match interpret_expr(expr2, labels, state, instr_infos, host)? {
+                Value::List(list) => list
+                    .into_iter()
+                    .map(|value| to_str(value, &clone))
+                    .collect::<Result<Vec<String>, Signal>>()?,
+                value => return Err(type_error(Value::List(Vec::new()), value, clone)),
+            };
+            let input = to_str(
+                interpret_expr(expr3, labels, state, instr_infos, host)?,
+                &clone,
+            )?;
+
+            check_permission(&clone, &state.options.permissions, Capability::Run)?;
+
+            return run(clone, program, args, input);
+        }
+
+        Fun::Find(expr1, expr2) => {
+            let s = to_str(
+                interpret_expr(expr1, labels, state, instr_infos, host)?,
+                &clone,
+            )?;
+            let needle = to_str(
+                interpret_expr(expr2, labels, state, instr_infos, host)?,
+                &clone,
+            )?;
+
+            match s.find(&needle) {
+                Some(n) => return Ok(Value::Int(s[..n].chars().count() as isize)),
+                None => return Ok(Value::Int(-1)),
+            }
+        }
+
+        Fun::Replace(expr1, expr2, expr3) => {
+            let s = to_str(
+                interpret_expr(expr1, labels, state, instr_infos, host)?,
+                &clone,
+            )?;
+            let from = to_str(
+                interpret_expr(expr2, labels, state, instr_infos, host)?,
+                &clone,
+            )?;
+            let to = to_str(
+                interpret_expr(expr3, labels, state, instr_infos, host)?,
+                &clone,
+            )?;
+
+            if from.is_empty() {
+                return Err(Signal::Error(Box::new(ErrorInfo::new(
+                    Error::ValueError(Value::Str(from)),
+                    clone,
+                    Some("Cannot replace an empty Str".to_string()),
+                ))));
+            }
+
+            return Ok(Value::Str(s.replace(&from, &to)));
+        }
+
+        Fun::Split(expr1, expr2, expr3) => {
+            let s = to_str(
+                interpret_expr(expr1, labels, state, instr_infos, host)?,
+                &clone,
+            )?;
+            let sep = to_str(
+                interpret_expr(expr2, labels, state, instr_infos, host)?,
+                &clone,
+            )?;
+            let slot = to_int(
+                interpret_expr(expr3, labels, state, instr_infos, host)?,
+                &clone,
+            )?;
+
+            if sep.is_empty() {
+                return Err(Signal::Error(Box::new(ErrorInfo::new(
+                    Error::ValueError(Value::Str(sep)),
+                    clone,
+                    Some("Cannot split using an empty Str".to_string()),
+                ))));
+            }
+
+            let mut count: isize = 0;
+
+            for part in s.split(&sep) {
+                let n = match slot.checked_add(count) {
+                    Some(n) => n,
+                    None => {
+                        return Err(Signal::Error(Box::new(ErrorInfo::new(
+                            Error::OverflowError,
+                            clone,
+                            Some("Ran out of slots to put the parts in".to_string()),
+                        ))))
+                    }
+                };
+
+                state.slots.insert(n, Value::Str(part.to_string()));
+                count += 1;
+            }
+
+            return Ok(Value::Int(count));
+        }
+
+        Fun::Trim(expr) | Fun::Upper(expr) | Fun::Lower(expr) => {
+            let s = to_str(
+                interpret_expr(expr, labels, state, instr_infos, host)?,
+                &clone,
+            )?;
+
+            return Ok(Value::Str(match clone {
+                Fun::Trim(..) => s.trim().to_string(),
+                Fun::Upper(..) => s.to_uppercase(),
+                _ => s.to_lowercase(),
+            }));
+        }
+
+        Fun::Repeat(expr1, expr2) => {
+            let s = to_str(
+                interpret_expr(expr1, labels, state, instr_infos, host)?,
+                &clone,
+            )?;
+            let value2 = interpret_expr(expr2, labels, state, instr_infos, host)?;
+            let times = to_int(value2.clone(), &clone)?;
+
+            if times < 0 {
+                return Err(Signal::Error(Box::new(ErrorInfo::new(
+                    Error::ValueError(value2),
+                    clone,
+                    Some("Cannot repeat a Str negative times".to_string()),
+                ))));
+            }
+
+            if times as u128 * s.len() as u128 > MAX_STR_LEN as u128 {
+                return Err(Signal::Error(Box::new(ErrorInfo::new(
+                    Error::OverflowError,
+                    clone,
+                    Some(format!(
+                        "The Str is too large to be repeated {} times",
+                        times
+                    )),
+                ))));
+            }
+
+            return Ok(Value::Str(s.repeat(times as usize)));
+        }
+
+        Fun::StartsWith(expr1, expr2) | Fun::EndsWith(expr1, expr2) => {
+            let s = to_str(
+                interpret_expr(expr1, labels, state, instr_infos, host)?,
+                &clone,
+            )?;
+            let affix = to_str(
+                interpret_expr(expr2, labels, state, instr_infos, host)?,
+                &clone,
+            )?;
+
+            let result = match clone {
+                Fun::StartsWith(..) => s.starts_with(&affix),
+                _ => s.ends_with(&affix),
+            };
+
+            return Ok(Value::Int(result as isize));
+        }
+
+        Fun::Text(expr) => {
+            let value = match expr {
+                Expr::Value(v) => v,
+                Expr::FunCall(_fun) => interpret_fun_call(_fun, labels, state, instr_infos, host)?,
+            };
+
+            match &value {
+                Value::Int(_) | Value::Float(_) => return Ok(Value::Str(value.text())),
+                #[cfg(feature = "bigint")]
+                Value::BigInt(_) => return Ok(Value::Str(value.text())),
+                _ => {
+                    return Err(Signal::Error(Box::new(ErrorInfo::new(
+                        Error::TypeError {
+                            expected: Value::Int(0),
+                            got: value.clone(),
+                        },
+                        clone,
+                        None,
+                    ))))
+                }
+            }
+        }
+
+        Fun::Number(expr) => {
+            let value = match expr {
+                Expr::Value(v) => v,
+                Expr::FunCall(_fun) => interpret_fun_call(_fun, labels, state, instr_infos, host)?,
+            };
+
+            match &value {
+                Value::Str(s) => match s.parse::<isize>() {
+                    Ok(int) => return Ok(Value::Int(int)),
+                    #[cfg(feature = "bigint")]
+                    Err(_) if state.options.big_ints && BigInt::parse(s).is_some() => {
+                        return Ok(BigInt::parse(s).unwrap().into())
+                    }
+                    // `inf` and `NaN` are left out, they are not something a user types in
+                    Err(_) if matches!(s.parse::<f64>(), Ok(float) if float.is_finite()) => {
+                        return Ok(Value::Float(s.parse().unwrap()))
+                    }
+                    Err(_) => {
+                        return Err(Signal::Error(Box::new(ErrorInfo::new(
+                            Error::ValueError(value.clone()),
+                            clone,
+                            Some(format!("Cannot convert {} to a number", value)),
+                        ))))
+                    }
+                },
+
+                _ => {
+                    return Err(Signal::Error(Box::new(ErrorInfo::new(
+                        Error::TypeError {
+                            expected: Value::Int(0),
+                            got: value.clone(),
+                        },
+                        clone,
+                        None,
+                    ))))
+                }
+            }
+        }
+
+        Fun::Convert(expr) => {
+            let value = match expr {
+                Expr::Value(v) => v,
+                Expr::FunCall(_fun) => interpret_fun_call(_fun, labels, state, instr_infos, host)?,
+            };
+
+            match value {
+                Value::Str(ref s) => {
+                    if s.len() != 1 {
+                        return Err(Signal::Error(Box::new(ErrorInfo::new(
+                            Error::ValueError(value.clone()),
                             clone,
                             Some(format!(
                                 "The Str should have exactly 1 char got {}",
                                 s.len()
                             )),
-                        )));
+                        ))));
                     }
 
                     let ch = s.chars().next().unwrap();
@@ -632,20 +1542,37 @@ pub fn interpret_fun_call(
                 Value::Int(int) => match char::from_u32(int as u32) {
                     Some(ch) => return Ok(Value::Str(ch.to_string())),
                     None => {
-                        return Err(Signal::Error(ErrorInfo::new(
+                        return Err(Signal::Error(Box::new(ErrorInfo::new(
                             Error::ValueError(value),
                             clone,
                             Some(format!("Cannot convert Int {} to a char", int)),
-                        )))
+                        ))))
                     }
                 },
 
+                #[cfg(feature = "bigint")]
+                Value::BigInt(..) => {
+                    return Err(Signal::Error(Box::new(ErrorInfo::new(
+                        Error::ValueError(value),
+                        clone,
+                        Some("Cannot convert an Int this large".to_string()),
+                    ))))
+                }
+
+                Value::Float(..) | Value::List(..) | Value::Map(..) => {
+                    return Err(Signal::Error(Box::new(ErrorInfo::new(
+                        Error::ValueError(value),
+                        clone,
+                        Some("Cannot convert a Float, a List or a Map".to_string()),
+                    ))))
+                }
+
                 Value::None => {
-                    return Err(Signal::Error(ErrorInfo::new(
+                    return Err(Signal::Error(Box::new(ErrorInfo::new(
                         Error::ValueError(value),
                         clone,
                         Some(format!("Cannot convert None value")),
-                    )))
+                    ))))
                 }
             }
         }
@@ -653,23 +1580,22 @@ pub fn interpret_fun_call(
         Fun::Return(expr) => {
             let value = match expr {
                 Expr::Value(v) => v,
-                Expr::FunCall(_fun) => {
-                    interpret_fun_call(_fun, labels, slots, instr_infos, stdout, stdin)?
-                }
+                Expr::FunCall(_fun) => interpret_fun_call(_fun, labels, state, instr_infos, host)?,
             };
 
             return Err(Signal::Return(value));
         }
 
         Fun::Input => {
-            let s = match stdin() {
-                Ok(s) => s,
+            let s = match (host.stdin)() {
+                Ok(Some(s)) => s,
+                Ok(None) => return Ok(Value::None),
                 Err(_) => {
-                    return Err(Signal::Error(ErrorInfo::new(
+                    return Err(Signal::Error(Box::new(ErrorInfo::new(
                         Error::Error(format!("Failed to receive an input")),
                         clone,
                         None,
-                    )))
+                    ))))
                 }
             };
 
@@ -678,26 +1604,755 @@ pub fn interpret_fun_call(
             return Ok(Value::Str(input));
         }
 
-        Fun::KeyChar => return Ok(Value::None),
+        Fun::Flush => {
+            let _ = (host.flush)();
+        }
+
+        Fun::ReadAll => {
+            let mut input = String::new();
+
+            loop {
+                match (host.stdin)() {
+                    Ok(Some(s)) => input.push_str(&s),
+                    Ok(None) => return Ok(Value::Str(input)),
+                    Err(_) => {
+                        return Err(Signal::Error(Box::new(ErrorInfo::new(
+                            Error::Error("Failed to receive an input".to_string()),
+                            clone,
+                            None,
+                        ))))
+                    }
+                }
+            }
+        }
+
+        Fun::KeyChar => match (host.keychar)() {
+            Ok(Some(ch)) => return Ok(Value::Str(ch.to_string())),
+            Ok(None) => return Ok(Value::None),
+            Err(_) => {
+                return Err(Signal::Error(Box::new(ErrorInfo::new(
+                    Error::Error("Failed to receive a key".to_string()),
+                    clone,
+                    None,
+                ))))
+            }
+        },
+
+        Fun::Millis => return Ok(Value::Int(host.clock.monotonic_ms() as isize)),
+
+        Fun::Time => return Ok(Value::Int(host.clock.unix_time() as isize)),
+
+        Fun::Sleep(expr) => {
+            let ms = to_int(
+                interpret_expr(expr, labels, state, instr_infos, host)?,
+                &clone,
+            )?;
+
+            if ms < 0 {
+                return Err(Signal::Error(Box::new(ErrorInfo::new(
+                    Error::ValueError(Value::Int(ms)),
+                    clone,
+                    Some("Can't sleep for a negative number of milliseconds".to_string()),
+                ))));
+            }
+
+            // whatever was written so far shows up before the pause
+            let _ = (host.flush)();
+            host.clock.sleep(ms as u64);
+        }
+
+        Fun::Date(expr1, expr2) => {
+            let timestamp = to_int(
+                interpret_expr(expr1, labels, state, instr_infos, host)?,
+                &clone,
+            )?;
+            let format = to_str(
+                interpret_expr(expr2, labels, state, instr_infos, host)?,
+                &clone,
+            )?;
+
+            match clock::format_date(timestamp as i64, &format) {
+                Ok(s) => return Ok(Value::Str(s)),
+                Err(specifier) => {
+                    return Err(Signal::Error(Box::new(ErrorInfo::new(
+                        Error::ValueError(Value::Str(format)),
+                        clone,
+                        Some(format!(
+                            "`{}` is not one of %Y, %m, %d, %H, %M, %S and %%",
+                            specifier
+                        )),
+                    ))))
+                }
+            }
+        }
 
         Fun::EmptySlot => {
             for n in 0..isize::MAX {
-                if !slots.contains_key(&n) {
+                if !state.slots.contains_key(&n) {
                     return Ok(Value::Int(n));
                 }
             }
 
-            return Err(Signal::Error(ErrorInfo::new(
+            return Err(Signal::Error(Box::new(ErrorInfo::new(
                 Error::NoSlotError,
                 clone,
                 Some(format!("At this point, you better use a known number")),
-            )));
+            ))));
         }
 
-        Fun::Exit => {
-            exit(0);
+        Fun::Try(expr) => {
+            let value = match expr {
+                Expr::Value(v) => v,
+                Expr::FunCall(_fun) => interpret_fun_call(_fun, labels, state, instr_infos, host)?,
+            };
+
+            let string = match value {
+                Value::Str(s) => s,
+                _ => {
+                    return Err(Signal::Error(Box::new(ErrorInfo::new(
+                        Error::TypeError {
+                            expected: Value::Str(String::from("")),
+                            got: value,
+                        },
+                        clone,
+                        None,
+                    ))));
+                }
+            };
+
+            match labels.get(&string) {
+                Some(i) => state.handlers.push(Handler {
+                    index: *i,
+                    depth: state.depth(),
+                }),
+                None => {
+                    return Err(Signal::Error(Box::new(ErrorInfo::new(
+                        Error::LabelError(string.to_string()),
+                        clone,
+                        None,
+                    ))));
+                }
+            }
+        }
+
+        Fun::EndTry => match state.handlers.last() {
+            Some(handler) if handler.depth == state.depth() => {
+                state.handlers.pop();
+            }
+            _ => {
+                return Err(Signal::Error(Box::new(ErrorInfo::new(
+                    Error::Error("There is no handler to remove".to_string()),
+                    clone.clone(),
+                    Some(format!(
+                        "Every `{}` should be paired with a `try:` of the same routine",
+                        &clone
+                    )),
+                ))));
+            }
+        },
+
+        Fun::Rethrow => match &state.caught {
+            Some(error_info) => return Err(Signal::Error(Box::new(error_info.clone()))),
+            None => {
+                return Err(Signal::Error(Box::new(ErrorInfo::new(
+                    Error::Error("There is no caught error to rethrow".to_string()),
+                    clone,
+                    None,
+                ))));
+            }
+        },
+
+        Fun::Exit(None) => return Err(Signal::Exit(0)),
+
+        Fun::Exit(Some(expr)) => {
+            let code = to_int(
+                interpret_expr(expr, labels, state, instr_infos, host)?,
+                &clone,
+            )?;
+
+            match i32::try_from(code) {
+                Ok(code) => return Err(Signal::Exit(code)),
+                Err(_) => {
+                    return Err(Signal::Error(Box::new(ErrorInfo::new(
+                        Error::ValueError(Value::Int(code)),
+                        clone,
+                        Some("The exit code doesn't fit in an i32".to_string()),
+                    ))))
+                }
+            }
         }
     }
 
     Ok(Value::None)
 }
+
+pub fn interpret_expr(
+    expr: Expr,
+    labels: &LabelType,
+    state: &mut State,
+    instr_infos: &Vec<InstrInfo>,
+    host: &mut Host,
+) -> Result<Value, Signal> {
+    match expr {
+        Expr::Value(v) => Ok(v),
+        Expr::FunCall(fun) => interpret_fun_call(fun, labels, state, instr_infos, host),
+    }
+}
+
+fn type_error(expected: Value, got: Value, fun: Fun) -> Signal {
+    Signal::Error(Box::new(ErrorInfo::new(
+        Error::TypeError { expected, got },
+        fun,
+        None,
+    )))
+}
+
+fn to_int(value: Value, fun: &Fun) -> Result<isize, Signal> {
+    match value {
+        Value::Int(int) => Ok(int),
+        #[cfg(feature = "bigint")]
+        Value::BigInt(_) => Err(Signal::Error(Box::new(ErrorInfo::new(
+            Error::OverflowError,
+            fun.clone(),
+            Some(format!("{} is too large to be used here", value.text())),
+        )))),
+        _ => Err(type_error(Value::Int(0), value, fun.clone())),
+    }
+}
+
+fn to_str(value: Value, fun: &Fun) -> Result<String, Signal> {
+    match value {
+        Value::Str(s) => Ok(s),
+        _ => Err(type_error(Value::Str(String::from("")), value, fun.clone())),
+    }
+}
+
+/// Turns the start and the end of a slice into a range within `len`,
+/// negative values counting from the end.
+fn slice_range(len: usize, start: isize, end: isize) -> (usize, usize) {
+    let clamp = |n: isize| {
+        if n < 0 {
+            (len as isize).saturating_add(n).max(0) as usize
+        } else {
+            (n as usize).min(len)
+        }
+    };
+
+    let start = clamp(start);
+    let end = clamp(end);
+
+    (start, end.max(start))
+}
+
+/// The slot holding the List or the Map that `push:`, `pop:`, `put:` and `del:` change in place,
+/// a local one for `lpush:`, `lpop:`, `lput:` and `ldel:`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Place {
+    Global(isize),
+    Local(isize),
+}
+
+impl fmt::Display for Place {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Place::Global(slot) => write!(f, "slot {}", slot),
+            Place::Local(slot) => write!(f, "local slot {}", slot),
+        }
+    }
+}
+
+/// Resolves the slot argument of the function.
+fn place_of(
+    expr: Expr,
+    labels: &LabelType,
+    state: &mut State,
+    instr_infos: &Vec<InstrInfo>,
+    host: &mut Host,
+    fun: &Fun,
+) -> Result<Place, Signal> {
+    let slot = to_int(interpret_expr(expr, labels, state, instr_infos, host)?, fun)?;
+
+    match fun {
+        Fun::LocalPush(..) | Fun::LocalPop(..) | Fun::LocalPut(..) | Fun::LocalDel(..) => {
+            Ok(Place::Local(slot))
+        }
+        _ => Ok(Place::Global(slot)),
+    }
+}
+
+fn place_value(state: &mut State, place: Place) -> Option<&mut Value> {
+    match place {
+        Place::Global(slot) => state.slots.get_mut(&slot),
+        Place::Local(slot) => state.locals().get_mut(&slot),
+    }
+}
+
+/// The List held by the slot, for the functions that modify it in place.
+fn slot_list<'a>(
+    state: &'a mut State,
+    place: Place,
+    fun: &Fun,
+) -> Result<&'a mut Vec<Value>, Signal> {
+    match place_value(state, place) {
+        Some(Value::List(list)) => Ok(list),
+        value => Err(type_error(
+            Value::List(Vec::new()),
+            value.cloned().unwrap_or(Value::None),
+            fun.clone(),
+        )),
+    }
+}
+
+/// The value as the key of a Map entry, only Strs and Ints can be keys.
+fn to_key(value: Value, fun: &Fun) -> Result<Key, Signal> {
+    match value {
+        Value::Int(int) => Ok(Key::Int(int)),
+        Value::Str(s) => Ok(Key::Str(s)),
+        _ => Err(type_error(Value::Str(String::from("")), value, fun.clone())),
+    }
+}
+
+/// Turns the index into a position within `len`, negative indices counting from the end.
+fn list_index(len: usize, index: isize) -> Option<usize> {
+    let n = if index < 0 {
+        (len as isize).checked_add(index)?
+    } else {
+        index
+    };
+
+    if 0 <= n && (n as usize) < len {
+        Some(n as usize)
+    } else {
+        None
+    }
+}
+
+fn overflow(fun: Fun, value1: &Value, value2: &Value) -> Signal {
+    Signal::Error(Box::new(ErrorInfo::new(
+        Error::OverflowError,
+        fun.clone(),
+        Some(format!(
+            "The result of `{}` with {} and {} is too large for an Int",
+            &fun, value1, value2
+        )),
+    )))
+}
+
+/// Ints and Floats are never converted implicitly, `float:` and `int:` are there for that.
+fn mixed_numbers(fun: Fun, value1: Value, value2: Value) -> Signal {
+    Signal::Error(Box::new(ErrorInfo::new(
+        Error::TypeError {
+            expected: value1,
+            got: value2,
+        },
+        fun,
+        Some("Ints and Floats can't be mixed, convert them with `float:` or `int:`".to_string()),
+    )))
+}
+
+fn float_result(fun: Fun, float: f64) -> Result<Value, Signal> {
+    if float.is_infinite() {
+        return Err(Signal::Error(Box::new(ErrorInfo::new(
+            Error::OverflowError,
+            fun.clone(),
+            Some(format!("The result of `{}` is too large for a Float", &fun)),
+        ))));
+    }
+
+    Ok(Value::Float(float))
+}
+
+/// The arithmetic functions taking two Ints or two Floats, all of them are checked for overflows.
+fn arithmetic(fun: Fun, value1: Value, value2: Value, big_ints: bool) -> Result<Value, Signal> {
+    let (int1, int2) = match (&value1, &value2) {
+        (Value::Int(int1), Value::Int(int2)) => (*int1, *int2),
+        #[cfg(feature = "bigint")]
+        (Value::BigInt(_), _) | (_, Value::BigInt(_)) if is_int(&value1) && is_int(&value2) => {
+            return big_arithmetic(fun, value1, value2)
+        }
+        (Value::Float(float1), Value::Float(float2)) => {
+            return float_arithmetic(fun, *float1, *float2)
+        }
+        (Value::Int(_), Value::Float(_)) | (Value::Float(_), Value::Int(_)) => {
+            return Err(mixed_numbers(fun, value1, value2))
+        }
+        _ => {
+            let got = match value1 {
+                Value::Float(_) => value2,
+                _ if is_int(&value1) => value2,
+                _ => value1,
+            };
+
+            return Err(Signal::Error(Box::new(ErrorInfo::new(
+                Error::TypeError {
+                    expected: Value::Int(0),
+                    got,
+                },
+                fun,
+                None,
+            ))));
+        }
+    };
+
+    if int2 == 0 && matches!(fun, Fun::Div(..) | Fun::Mod(..)) {
+        return Err(Signal::Error(Box::new(ErrorInfo::new(
+            Error::ZeroDivisionError,
+            fun.clone(),
+            Some(format!("Function `{}` can't divide {} by zero", &fun, int1)),
+        ))));
+    }
+
+    let result = match fun {
+        Fun::Sub(..) => int1.checked_sub(int2),
+        Fun::Mul(..) => int1.checked_mul(int2),
+        Fun::Div(..) => int1.checked_div(int2),
+        Fun::Mod(..) => int1.checked_rem(int2),
+        Fun::Pow(..) => match u32::try_from(int2) {
+            Ok(exp) => int1.checked_pow(exp),
+            Err(_) if int2 < 0 => {
+                return Err(Signal::Error(Box::new(ErrorInfo::new(
+                    Error::ValueError(value2),
+                    fun,
+                    Some("The exponent can't be negative".to_string()),
+                ))))
+            }
+            // the powers of these stay small however large the exponent is
+            Err(_) if int1 == 0 || int1 == 1 => Some(int1),
+            Err(_) if int1 == -1 => Some(if int2 % 2 == 0 { 1 } else { -1 }),
+            Err(_) => None,
+        },
+        Fun::Min(..) => Some(int1.min(int2)),
+        Fun::Max(..) => Some(int1.max(int2)),
+        _ => panic!("Got `{}`. (This error is not supposed to occur.)", fun),
+    };
+
+    match result {
+        Some(int) => Ok(Value::Int(int)),
+        None => overflowed(fun, value1, value2, big_ints),
+    }
+}
+
+/// The bitwise functions taking two Ints, shifting left is checked for overflows like `mul:`.
+fn bitwise(fun: Fun, int1: isize, int2: isize) -> Result<Value, Signal> {
+    let shift = match fun {
+        Fun::Shl(..) | Fun::Shr(..) => match u32::try_from(int2) {
+            Ok(shift) if shift < isize::BITS => shift,
+            _ => {
+                return Err(Signal::Error(Box::new(ErrorInfo::new(
+                    Error::ValueError(Value::Int(int2)),
+                    fun,
+                    Some(format!("Can only shift by 0 to {} bits", isize::BITS - 1)),
+                ))))
+            }
+        },
+        _ => 0,
+    };
+
+    let int = match fun {
+        Fun::BitAnd(..) => int1 & int2,
+        Fun::BitOr(..) => int1 | int2,
+        Fun::BitXor(..) => int1 ^ int2,
+        Fun::Shl(..) if (int1 << shift) >> shift == int1 => int1 << shift,
+        Fun::Shl(..) => return Err(overflow(fun, &Value::Int(int1), &Value::Int(int2))),
+        Fun::Shr(..) => int1 >> shift,
+        _ => panic!("Got `{}`. (This error is not supposed to occur.)", fun),
+    };
+
+    Ok(Value::Int(int))
+}
+
+/// Raises `PermissionError` unless the capability (`Env` or `Run`) is granted.
+/// The capabilities limited to directories are checked by `check_access` instead.
+fn check_permission(
+    fun: &Fun,
+    permissions: &Permissions,
+    capability: Capability,
+) -> Result<(), Signal> {
+    let granted = match capability {
+        Capability::Env => permissions.env,
+        Capability::Run => permissions.run,
+        Capability::Read | Capability::Write => panic!(
+            "Got `{}` which needs a path. (This error is not supposed to occur.)",
+            capability
+        ),
+    };
+
+    if granted {
+        return Ok(());
+    }
+
+    Err(Signal::Error(Box::new(ErrorInfo::new(
+        Error::PermissionError(capability),
+        fun.clone(),
+        Some(format!(
+            "Missing the `{}` capability, pass `{}` to grant it",
+            capability,
+            capability.flag()
+        )),
+    ))))
+}
+
+/// Raises `PermissionError` unless the path lies within one of the directories
+/// the capability (`Read` or `Write`) is granted for.
+fn check_access(
+    fun: &Fun,
+    path: &str,
+    permissions: &Permissions,
+    capability: Capability,
+) -> Result<(), Signal> {
+    let dirs = match capability {
+        Capability::Read => &permissions.read,
+        Capability::Write => &permissions.write,
+        Capability::Env | Capability::Run => panic!(
+            "Got `{}` which takes no path. (This error is not supposed to occur.)",
+            capability
+        ),
+    };
+    let path = Path::new(path);
+
+    let allowed = match full_path(path) {
+        Some(full_path) => dirs
+            .iter()
+            .any(|dir| matches!(fs::canonicalize(dir), Ok(dir) if full_path.starts_with(&dir))),
+        None => false,
+    };
+
+    if allowed {
+        return Ok(());
+    }
+
+    Err(Signal::Error(Box::new(ErrorInfo::new(
+        Error::PermissionError(capability),
+        fun.clone(),
+        Some(format!(
+            "Missing the `{}` capability for `{}`, pass `{}=DIR` to grant it for the files within DIR",
+            capability,
+            path.display(),
+            capability.flag()
+        )),
+    ))))
+}
+
+/// How many symlinks `full_path` follows before giving up, like the system does.
+const MAX_SYMLINKS: usize = 40;
+
+/// The absolute form of the path, for paths that may not exist yet.
+/// The nearest ancestor that exists is canonicalized, which resolves its symlinks,
+/// and the rest of the path is added to it lexically.
+fn full_path(path: &Path) -> Option<PathBuf> {
+    resolve_path(std::env::current_dir().ok()?.join(path), MAX_SYMLINKS)
+}
+
+fn resolve_path(path: PathBuf, links: usize) -> Option<PathBuf> {
+    let components: Vec<Component> = path.components().collect();
+
+    for k in (1..=components.len()).rev() {
+        let ancestor: PathBuf = components[..k].iter().collect();
+
+        if let Ok(mut full_path) = fs::canonicalize(&ancestor) {
+            for (n, component) in components[k..].iter().enumerate() {
+                match component {
+                    Component::ParentDir => {
+                        full_path.pop();
+                    }
+                    Component::Normal(name) => {
+                        full_path.push(name);
+
+                        // a dangling symlink can't be canonicalized, so it's followed here,
+                        // else it could point outside of the allowed directories
+                        if let Ok(target) = fs::read_link(&full_path) {
+                            if links == 0 {
+                                return None;
+                            }
+
+                            full_path.pop();
+                            let mut target = full_path.join(target);
+                            target.extend(&components[k + n + 1..]);
+
+                            return resolve_path(target, links - 1);
+                        }
+                    }
+                    _ => {}
+                }
+            }
+
+            return Some(full_path);
+        }
+    }
+
+    None
+}
+
+/// Runs the program to its end, returning a Map of its `stdout`, `stderr` and exit `status`,
+/// the status being None if the program was stopped by a signal.
+fn run(fun: Fun, program: String, args: Vec<String>, input: String) -> Result<Value, Signal> {
+    let child = Command::new(&program)
+        .args(&args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn();
+
+    let mut child = match child {
+        Ok(child) => child,
+        Err(err) => {
+            return Err(Signal::Error(Box::new(ErrorInfo::new(
+                Error::ProcessError(program),
+                fun,
+                Some(err.to_string()),
+            ))))
+        }
+    };
+
+    // written from another thread so that a program that doesn't read all of its input
+    // before writing its output can't block
+    let mut stdin = child.stdin.take().unwrap();
+    let writer = thread::spawn(move || {
+        let _ = stdin.write_all(input.as_bytes());
+    });
+
+    let output = child.wait_with_output();
+    let _ = writer.join();
+
+    let output = match output {
+        Ok(output) => output,
+        Err(err) => {
+            return Err(Signal::Error(Box::new(ErrorInfo::new(
+                Error::ProcessError(program),
+                fun,
+                Some(err.to_string()),
+            ))))
+        }
+    };
+
+    let mut map = BTreeMap::new();
+    map.insert(
+        Key::Str("stdout".to_string()),
+        Value::Str(String::from_utf8_lossy(&output.stdout).to_string()),
+    );
+    map.insert(
+        Key::Str("stderr".to_string()),
+        Value::Str(String::from_utf8_lossy(&output.stderr).to_string()),
+    );
+    map.insert(
+        Key::Str("status".to_string()),
+        match output.status.code() {
+            Some(code) => Value::Int(code as isize),
+            None => Value::None,
+        },
+    );
+
+    Ok(Value::Map(map))
+}
+
+fn io_error(fun: Fun, path: String, err: io::Error) -> Signal {
+    Signal::Error(Box::new(ErrorInfo::new(
+        Error::IoError(path),
+        fun,
+        Some(err.to_string()),
+    )))
+}
+
+/// Whether the value is an Int, big or not.
+fn is_int(value: &Value) -> bool {
+    match value {
+        Value::Int(_) => true,
+        #[cfg(feature = "bigint")]
+        Value::BigInt(_) => true,
+        _ => false,
+    }
+}
+
+/// Redoes the Int arithmetic that overflowed with big ints if they are enabled.
+#[cfg(feature = "bigint")]
+fn overflowed(fun: Fun, value1: Value, value2: Value, big_ints: bool) -> Result<Value, Signal> {
+    if big_ints {
+        big_arithmetic(fun, value1, value2)
+    } else {
+        Err(overflow(fun, &value1, &value2))
+    }
+}
+
+#[cfg(not(feature = "bigint"))]
+fn overflowed(fun: Fun, value1: Value, value2: Value, _big_ints: bool) -> Result<Value, Signal> {
+    Err(overflow(fun, &value1, &value2))
+}
+
+/// The Strs `repeat:` builds are limited (to 1 GiB) so that a mistyped count
+/// raises `OverflowError` instead of eating up all the memory.
+const MAX_STR_LEN: usize = 1 << 30;
+
+/// The exponents are limited so that a mistyped `pow:` doesn't eat up all the memory.
+#[cfg(feature = "bigint")]
+const MAX_POW_SIZE: f64 = 20_000.0;
+
+/// The arithmetic of `a:` and the functions of `arithmetic` on two Ints, at least one of them big.
+#[cfg(feature = "bigint")]
+fn big_arithmetic(fun: Fun, value1: Value, value2: Value) -> Result<Value, Signal> {
+    let big1 = BigInt::try_from(&value1).unwrap();
+    let big2 = BigInt::try_from(&value2).unwrap();
+
+    if big2.is_zero() && matches!(fun, Fun::Div(..) | Fun::Mod(..)) {
+        return Err(Signal::Error(Box::new(ErrorInfo::new(
+            Error::ZeroDivisionError,
+            fun.clone(),
+            Some(format!("Function `{}` can't divide {} by zero", &fun, big1)),
+        ))));
+    }
+
+    let result = match fun {
+        Fun::Add(..) => &big1 + &big2,
+        Fun::Sub(..) => &big1 - &big2,
+        Fun::Mul(..) => &big1 * &big2,
+        Fun::Div(..) => big1.div_rem(&big2).unwrap().0,
+        Fun::Mod(..) => big1.div_rem(&big2).unwrap().1,
+        Fun::Pow(..) => match big2.to_isize().map(u32::try_from) {
+            _ if big2 < BigInt::from(0) => {
+                return Err(Signal::Error(Box::new(ErrorInfo::new(
+                    Error::ValueError(value2),
+                    fun,
+                    Some("The exponent can't be negative".to_string()),
+                ))))
+            }
+            Some(Ok(exp)) if big1.pow_size(exp) <= MAX_POW_SIZE => big1.pow(exp),
+            // the powers of these stay small however large the exponent is
+            _ if big1.is_zero() || big1 == BigInt::from(1) => big1,
+            _ if big1 == BigInt::from(-1) => match big2.div_rem(&BigInt::from(2)) {
+                Some((_, rem)) if rem.is_zero() => BigInt::from(1),
+                _ => big1,
+            },
+            _ => return Err(overflow(fun, &value1, &value2)),
+        },
+        Fun::Min(..) => big1.min(big2),
+        Fun::Max(..) => big1.max(big2),
+        _ => panic!("Got `{}`. (This error is not supposed to occur.)", fun),
+    };
+
+    Ok(result.into())
+}
+
+fn float_arithmetic(fun: Fun, float1: f64, float2: f64) -> Result<Value, Signal> {
+    if float2 == 0.0 && matches!(fun, Fun::Div(..) | Fun::Mod(..)) {
+        return Err(Signal::Error(Box::new(ErrorInfo::new(
+            Error::ZeroDivisionError,
+            fun.clone(),
+            Some(format!(
+                "Function `{}` can't divide {:?} by zero",
+                &fun, float1
+            )),
+        ))));
+    }
+
+    let result = match fun {
+        Fun::Sub(..) => float1 - float2,
+        Fun::Mul(..) => float1 * float2,
+        Fun::Div(..) => float1 / float2,
+        Fun::Mod(..) => float1 % float2,
+        Fun::Pow(..) => float1.powf(float2),
+        Fun::Min(..) => float1.min(float2),
+        Fun::Max(..) => float1.max(float2),
+        _ => panic!("Got `{}`. (This error is not supposed to occur.)", fun),
+    };
+
+    float_result(fun, result)
+}
@@ -0,0 +1,245 @@
+use crate::macros;
+use crate::parser::{self, Instr, InstrInfo, ParseError, ParseErrorInfo};
+use crate::scanner::Char;
+use crate::tokenizer::{self, Token, TokenInfo, TokenizerError};
+use codespan_reporting::files::SimpleFiles;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+type LabelType = HashMap<String, usize>;
+
+/// An error of the file with the given id.
+#[derive(Debug, Clone, PartialEq)]
+pub enum LoadError {
+    TokenizerError(usize, TokenizerError),
+    ParseError(usize, ParseErrorInfo),
+}
+
+/// A file included using `&"file.mc"` or `&"file.mc" namespace`.
+struct Include {
+    path: String,
+    namespace: Option<String>,
+    line: Vec<TokenInfo>,
+}
+
+struct Loader<'a> {
+    files: &'a mut SimpleFiles<String, String>,
+    labels: LabelType,
+    instr_infos: Vec<InstrInfo>,
+    taken_slots: HashSet<isize>,
+    namespaces: HashMap<String, PathBuf>,
+    loaded: HashSet<PathBuf>,
+    stack: Vec<PathBuf>,
+    big_ints: bool,
+}
+
+/// Tokenizes and parses the file along with all the files it includes.
+/// The instructions of the file come first, followed by the ones of each included file,
+/// every file ending with an `Instr::EndOfFile` so that the execution doesn't run into the next one.
+pub fn load(
+    files: &mut SimpleFiles<String, String>,
+    file_name: &str,
+    source: String,
+    path: Option<&Path>,
+    big_ints: bool,
+) -> Result<(LabelType, Vec<InstrInfo>), LoadError> {
+    let file_id = files.add(file_name.to_string(), source);
+
+    let mut loader = Loader {
+        files,
+        labels: HashMap::new(),
+        instr_infos: Vec::new(),
+        taken_slots: HashSet::new(),
+        namespaces: HashMap::new(),
+        loaded: HashSet::new(),
+        stack: Vec::new(),
+        big_ints,
+    };
+
+    let path = path.map(|path| fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf()));
+
+    if let Some(path) = &path {
+        loader.loaded.insert(path.clone());
+        loader.stack.push(path.clone());
+    }
+
+    loader.load_file(file_id, path.as_deref(), None)?;
+
+    Ok((loader.labels, loader.instr_infos))
+}
+
+impl Loader<'_> {
+    fn load_file(
+        &mut self,
+        file_id: usize,
+        path: Option<&Path>,
+        namespace: Option<&str>,
+    ) -> Result<(), LoadError> {
+        let source = self.files.get(file_id).unwrap().source().clone();
+        let source_chars = Char::from_source(&source);
+
+        let token_infos = tokenizer::tokenize(source_chars, self.big_ints)
+            .map_err(|err| LoadError::TokenizerError(file_id, err))?;
+
+        let token_infos =
+            macros::expand(token_infos).map_err(|err| LoadError::ParseError(file_id, err))?;
+
+        let includes =
+            find_includes(&token_infos).map_err(|err| LoadError::ParseError(file_id, err))?;
+
+        let (labels, instr_infos) = parser::parse(token_infos, namespace, &mut self.taken_slots)
+            .map_err(|err| LoadError::ParseError(file_id, err))?;
+
+        // labels of the modules always have a namespace so they can't collide with each other
+        let offset = self.instr_infos.len();
+        for (label, index) in labels.into_iter() {
+            self.labels.insert(label, index + offset);
+        }
+
+        for mut instr_info in instr_infos.into_iter() {
+            instr_info.file_id = file_id;
+            self.instr_infos.push(instr_info);
+        }
+
+        let mut end_of_file = InstrInfo::new(Instr::EndOfFile, source.len(), source.len());
+        end_of_file.file_id = file_id;
+        self.instr_infos.push(end_of_file);
+
+        let dir = match path.and_then(|path| path.parent()) {
+            Some(dir) => dir.to_path_buf(),
+            None => PathBuf::from("."),
+        };
+
+        for include in includes.into_iter() {
+            self.include(file_id, &dir, include)?;
+        }
+
+        Ok(())
+    }
+
+    fn include(&mut self, file_id: usize, dir: &Path, include: Include) -> Result<(), LoadError> {
+        let token_info = include.line[1].clone();
+
+        let fail = |msg: String| {
+            LoadError::ParseError(
+                file_id,
+                ParseErrorInfo::new(
+                    ParseError::IncludeFailed(token_info.clone()),
+                    include.line.clone(),
+                    Some(msg),
+                ),
+            )
+        };
+
+        let path = match fs::canonicalize(dir.join(&include.path)) {
+            Ok(path) => path,
+            Err(err) => return Err(fail(format!("Cannot include `{}`: {}", &include.path, err))),
+        };
+
+        if let Some(n) = self.stack.iter().position(|p| p == &path) {
+            let mut cycle = self.stack[n..]
+                .iter()
+                .map(|p| p.display().to_string())
+                .collect::<Vec<String>>();
+            cycle.push(path.display().to_string());
+
+            return Err(LoadError::ParseError(
+                file_id,
+                ParseErrorInfo::new(
+                    ParseError::IncludeCycle(token_info.clone()),
+                    include.line.clone(),
+                    Some(format!("The include cycle is: {}", cycle.join(" -> "))),
+                ),
+            ));
+        }
+
+        // a file is only loaded once, even if it's included by multiple files
+        if self.loaded.contains(&path) {
+            return Ok(());
+        }
+
+        let namespace = match include.namespace {
+            Some(ref namespace) => namespace.to_string(),
+            None => match path.file_stem() {
+                Some(stem) => stem.to_string_lossy().to_string(),
+                None => return Err(fail(format!("Cannot name the module `{}`", &include.path))),
+            },
+        };
+
+        if let Some(other) = self.namespaces.get(&namespace) {
+            return Err(fail(format!(
+                "The namespace `{}` is already used by `{}`",
+                &namespace,
+                other.display()
+            )));
+        }
+
+        let source = match fs::read_to_string(&path) {
+            Ok(source) => source,
+            Err(err) => return Err(fail(format!("Cannot include `{}`: {}", &include.path, err))),
+        };
+
+        let name = dir.join(&include.path).display().to_string();
+        let child_id = self.files.add(name, source);
+
+        self.namespaces.insert(namespace.clone(), path.clone());
+        self.loaded.insert(path.clone());
+        self.stack.push(path.clone());
+
+        self.load_file(child_id, Some(&path), Some(&namespace))?;
+
+        self.stack.pop();
+
+        Ok(())
+    }
+}
+
+/// Collects the `&"file.mc"` lines.
+fn find_includes(token_infos: &[TokenInfo]) -> Result<Vec<Include>, ParseErrorInfo> {
+    let mut includes = Vec::new();
+
+    for line in token_infos.split(|token_info| token_info.token == Token::Eol) {
+        let mut token_line = Vec::new();
+
+        for token_info in line.iter() {
+            token_line.push(token_info.token.clone());
+        }
+
+        match &token_line[..] {
+            [Token::Amp, Token::Str(path)] => includes.push(Include {
+                path: path.to_string(),
+                namespace: None,
+                line: line.to_vec(),
+            }),
+
+            [Token::Amp, Token::Str(path), Token::Idn(namespace)] => includes.push(Include {
+                path: path.to_string(),
+                namespace: Some(namespace.to_string()),
+                line: line.to_vec(),
+            }),
+
+            [Token::Amp, ..] => {
+                let unexpected = match &token_line[..] {
+                    [Token::Amp] => line[0].clone(),
+                    [Token::Amp, Token::Str(_), Token::Idn(_), ..] => line[3].clone(),
+                    [Token::Amp, Token::Str(_), ..] => line[2].clone(),
+                    _ => line[1].clone(),
+                };
+
+                return Err(ParseErrorInfo::new(
+                    ParseError::UnexpectedToken(unexpected),
+                    line.to_vec(),
+                    Some(
+                        "Files are included like `&\"file.mc\"` or `&\"file.mc\" namespace`"
+                            .to_string(),
+                    ),
+                ));
+            }
+
+            _ => {}
+        }
+    }
+
+    Ok(includes)
+}
@@ -0,0 +1,301 @@
+use crate::parser::{self, ParseError, ParseErrorInfo};
+use crate::tokenizer::{Token, TokenInfo};
+use std::collections::HashMap;
+
+/// How deep macros may expand inside of other macros, which stops the ones that expand to themselves.
+const MAX_DEPTH: usize = 64;
+
+#[derive(Debug, Clone, PartialEq)]
+struct Macro {
+    params: Vec<String>,
+    body: Vec<TokenInfo>,
+}
+
+/// Removes the macro definitions from the tokens and expands the uses of the macros.
+///
+/// A macro is defined by the line `%:name param1 param2` and the lines up to the one
+/// holding just `%`. It's used with `%name arg1, arg2` where the arguments are the
+/// tokens up to the end of the line, separated by `,`. Inside the body, `%param` is
+/// replaced with the tokens of the argument.
+///
+/// The labels defined inside of the body are renamed for each expansion, along with
+/// the Strs naming them, so that a macro can be used more than once.
+pub fn expand(token_infos: Vec<TokenInfo>) -> Result<Vec<TokenInfo>, ParseErrorInfo> {
+    let mut macros: HashMap<String, Macro> = HashMap::new();
+    let mut macro_lines: HashMap<String, Vec<TokenInfo>> = HashMap::new();
+    let mut rest = Vec::new();
+
+    let mut lines = token_infos.split_inclusive(|token_info| token_info.token == Token::Eol);
+
+    while let Some(line) = lines.next() {
+        let header = strip_eol(line);
+
+        match header.get(0..2).map(|t| (&t[0].token, &t[1].token)) {
+            Some((Token::Pct, Token::Col)) => {}
+            _ => {
+                rest.extend_from_slice(line);
+                continue;
+            }
+        }
+
+        let mut params = Vec::new();
+
+        for token_info in header[2..].iter() {
+            match &token_info.token {
+                Token::Idn(idn) => params.push(idn.to_string()),
+                _ => {
+                    return Err(ParseErrorInfo::new(
+                        ParseError::UnexpectedToken(token_info.clone()),
+                        header.to_vec(),
+                        Some("Macros are defined like `%:name param1 param2`".to_string()),
+                    ))
+                }
+            }
+        }
+
+        if params.is_empty() {
+            return Err(ParseErrorInfo::new(
+                ParseError::InvalidSyntax,
+                header.to_vec(),
+                Some("The macro has no name".to_string()),
+            ));
+        }
+
+        let name = params.remove(0);
+
+        if let Some(line_at) = macro_lines.get(&name) {
+            return Err(ParseErrorInfo::new(
+                ParseError::MacroAlreadyDefined {
+                    name,
+                    line: line_at.clone(),
+                },
+                header.to_vec(),
+                None,
+            ));
+        }
+
+        let mut body = Vec::new();
+        let mut terminated = false;
+
+        for line in lines.by_ref() {
+            let stripped = strip_eol(line);
+
+            if stripped.len() == 1 && stripped[0].token == Token::Pct {
+                terminated = true;
+                break;
+            }
+
+            body.extend_from_slice(line);
+        }
+
+        if !terminated {
+            return Err(ParseErrorInfo::new(
+                ParseError::UnterminatedMacro(header[2].clone()),
+                header.to_vec(),
+                Some("The body of a macro ends with a line holding just `%`".to_string()),
+            ));
+        }
+
+        macro_lines.insert(name.to_string(), header.to_vec());
+        macros.insert(name, Macro { params, body });
+    }
+
+    let mut count = 0;
+    expand_tokens(rest, &macros, 0, &mut count)
+}
+
+fn strip_eol(line: &[TokenInfo]) -> &[TokenInfo] {
+    match line.last() {
+        Some(token_info) if token_info.token == Token::Eol => &line[..line.len() - 1],
+        _ => line,
+    }
+}
+
+fn expand_tokens(
+    token_infos: Vec<TokenInfo>,
+    macros: &HashMap<String, Macro>,
+    depth: usize,
+    count: &mut usize,
+) -> Result<Vec<TokenInfo>, ParseErrorInfo> {
+    let mut expanded = Vec::new();
+    let mut i = 0;
+
+    while i < token_infos.len() {
+        if token_infos[i].token != Token::Pct {
+            expanded.push(token_infos[i].clone());
+            i += 1;
+            continue;
+        }
+
+        // the use of the macro spans up to the end of the line
+        let mut j = i;
+        while j < token_infos.len() && token_infos[j].token != Token::Eol {
+            j += 1;
+        }
+        let line = token_infos[i..j].to_vec();
+
+        let (name, token_info) = match line.get(1).map(|t| &t.token) {
+            Some(Token::Idn(idn)) => (idn.to_string(), line[1].clone()),
+            _ => {
+                return Err(ParseErrorInfo::new(
+                    ParseError::UnexpectedToken(line.get(1).unwrap_or(&line[0]).clone()),
+                    line.clone(),
+                    Some("Macros are used like `%name arg1, arg2`".to_string()),
+                ))
+            }
+        };
+
+        let mac = match macros.get(&name) {
+            Some(mac) => mac,
+            None => {
+                return Err(ParseErrorInfo::new(
+                    ParseError::UndefinedMacro(token_info),
+                    line,
+                    None,
+                ))
+            }
+        };
+
+        if depth >= MAX_DEPTH {
+            return Err(ParseErrorInfo::new(
+                ParseError::MacroRecursionLimit(token_info),
+                line,
+                Some(format!(
+                    "Macros can be expanded at most {} times inside each other",
+                    MAX_DEPTH
+                )),
+            ));
+        }
+
+        let mut args: Vec<Vec<TokenInfo>> = line[2..]
+            .split(|token_info| token_info.token == Token::Com)
+            .map(|arg| arg.to_vec())
+            .collect();
+
+        if line.len() == 2 {
+            args.clear();
+        }
+
+        if args.len() != mac.params.len() {
+            return Err(ParseErrorInfo::new(
+                ParseError::MacroArgumentMismatch {
+                    token_info,
+                    got: args.len(),
+                    expected: mac.params.len(),
+                },
+                line,
+                None,
+            ));
+        }
+
+        *count += 1;
+        let start = line[0].start;
+        let end = line[line.len() - 1].end;
+
+        let body = rename_labels(&mac.body, *count);
+        let mut substituted = Vec::new();
+        let mut k = 0;
+
+        while k < body.len() {
+            if let (Token::Pct, Some(Token::Idn(idn))) =
+                (&body[k].token, body.get(k + 1).map(|t| &t.token))
+            {
+                if let Some(n) = mac.params.iter().position(|param| param == idn) {
+                    substituted.extend(args[n].iter().cloned());
+                    k += 2;
+                    continue;
+                }
+            }
+
+            let mut token_info = body[k].clone();
+            if token_info.expanded_from.is_none() {
+                token_info.expanded_from = Some(Box::new(body[k].clone()));
+            }
+            token_info.start = start;
+            token_info.end = end;
+
+            substituted.push(token_info);
+            k += 1;
+        }
+
+        expanded.extend(expand_tokens(substituted, macros, depth + 1, count)?);
+        i = j;
+    }
+
+    Ok(expanded)
+}
+
+/// Gives the labels defined in the body, and the Strs naming them, a name unique to the expansion.
+/// A Str names a label when it's the label argument of `j:`, `f:`, `fN:`, `#:` or `try:`,
+/// or an argument of a macro used in the body, which may pass it on to one of those.
+fn rename_labels(body: &[TokenInfo], count: usize) -> Vec<TokenInfo> {
+    let mut labels = Vec::new();
+
+    for line in body.split(|token_info| token_info.token == Token::Eol) {
+        if let [Token::Smi, Token::Idn(idn)] = tokens_of(line).as_slice() {
+            labels.push(idn.to_string());
+        }
+    }
+
+    let rename = |s: &str| format!("{}%{}", s, count);
+    let mut renamed = Vec::new();
+
+    for line in body.split(|token_info| token_info.token == Token::Eol) {
+        let tokens = tokens_of(line);
+        let macro_use = matches!(tokens.as_slice(), [Token::Pct, Token::Idn(_), ..]);
+
+        for (k, token_info) in line.iter().enumerate() {
+            let mut token_info = token_info.clone();
+
+            match &token_info.token {
+                Token::Idn(s) if k == 1 && tokens[0] == Token::Smi && labels.contains(s) => {
+                    token_info.token = Token::Idn(rename(s));
+                }
+                Token::Str(s) if labels.contains(s) && (macro_use || is_label_arg(&tokens, k)) => {
+                    token_info.token = Token::Str(rename(s));
+                }
+                _ => {}
+            }
+
+            renamed.push(token_info);
+        }
+
+        // the lines were split on these
+        if let Some(eol) = body.get(renamed.len()) {
+            renamed.push(eol.clone());
+        }
+    }
+
+    renamed
+}
+
+fn tokens_of(line: &[TokenInfo]) -> Vec<Token> {
+    line.iter()
+        .map(|token_info| token_info.token.clone())
+        .collect()
+}
+
+/// Whether the token at `k` is the first argument of a function that takes a label.
+fn is_label_arg(tokens: &[Token], k: usize) -> bool {
+    if k < 2 || tokens[k - 1] != Token::Col {
+        return false;
+    }
+
+    match &tokens[k - 2] {
+        Token::Hsh => true,
+        Token::Idn(s) => s == "j" || s == "f" || s == "try" || parser::fun_jump_arity(s).is_some(),
+        _ => false,
+    }
+}
+
+/// The span of the macro body the tokens of the line are expanded from, if any.
+pub fn expansion_span(line: &[TokenInfo]) -> Option<(usize, usize)> {
+    let mut expanded = line
+        .iter()
+        .filter_map(|token_info| token_info.expanded_from.as_ref());
+
+    let first = expanded.next()?;
+    let last = expanded.next_back().unwrap_or(first);
+
+    Some((first.start, last.end))
+}
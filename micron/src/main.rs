@@ -1,7 +1,9 @@
 // use std::env;
+use std::cell::RefCell;
 use std::error::Error;
 use std::fs::File;
-use std::io::{self, Read, Write};
+use std::io::{self, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
 use std::process::exit;
 
 use clap::clap_app;
@@ -10,14 +12,23 @@ use codespan_reporting::files::SimpleFiles;
 use codespan_reporting::term::termcolor::{ColorChoice, StandardStream};
 use codespan_reporting::term::{self /*ColorArg*/};
 
+#[cfg(feature = "bigint")]
+mod bigint;
+mod clock;
 mod errors;
 mod interpreter;
+mod loader;
+mod macros;
 mod parser;
+mod random;
 mod scanner;
+mod terminal;
 mod tokenizer;
 
+use clock::{Clock, FakeClock, SystemClock};
 #[allow(unused_imports)]
 use interpreter::{ErrorInfo, InterpreterError};
+use loader::LoadError;
 use parser::ParseError;
 use tokenizer::TokenizerError;
 
@@ -30,8 +41,15 @@ fn file_read(file_name: &str) -> Result<String, Box<dyn Error>> {
     Ok(content)
 }
 
+fn dirs_of(matches: &clap::ArgMatches, name: &str) -> Vec<PathBuf> {
+    match matches.values_of(name) {
+        Some(values) => values.map(PathBuf::from).collect(),
+        None => Vec::new(),
+    }
+}
+
 fn main() {
-    let matches = clap_app!(micron =>
+    let app = clap_app!(micron =>
         (version: "1.0")
         (author: "LoystonLive")
         (about: "A micron rust variant")
@@ -39,8 +57,74 @@ fn main() {
         (@arg debug: -d --debug "Print parsing information")
         (@arg pretty: -p --pretty "Prettifies the debug")
         (@arg compileonly: --compileonly "Compiles but doesn't run")
-    )
-    .get_matches();
+    );
+
+    #[cfg(feature = "bigint")]
+    let app = app.arg(
+        clap::Arg::new("bigints")
+            .long("big-ints")
+            .help("Ints grow as large as needed instead of overflowing"),
+    );
+
+    let app = app
+        .arg(
+            clap::Arg::new("seed")
+                .long("seed")
+                .value_name("N")
+                .takes_value(true)
+                .validator(|s| s.parse::<u64>())
+                .help("Seeds the random numbers so that they are the same on every run"),
+        )
+        // only meant for the tests, embedders supply their own `Clock` instead
+        .arg(
+            clap::Arg::new("fake_clock")
+                .long("fake-clock")
+                .value_name("TIME")
+                .takes_value(true)
+                .allow_hyphen_values(true)
+                .validator(|s| s.parse::<i64>())
+                .hide(true)
+                .help("Starts the clock at the Unix TIME and makes sleep: instant"),
+        )
+        .arg(
+            clap::Arg::new("allow_read")
+                .long("allow-read")
+                .value_name("DIR")
+                .takes_value(true)
+                .multiple_occurrences(true)
+                .help("Lets the script read the files within DIR"),
+        )
+        .arg(
+            clap::Arg::new("allow_write")
+                .long("allow-write")
+                .value_name("DIR")
+                .takes_value(true)
+                .multiple_occurrences(true)
+                .help("Lets the script write the files within DIR"),
+        )
+        .arg(
+            clap::Arg::new("allow_env")
+                .long("allow-env")
+                .help("Lets the script read the environment variables"),
+        )
+        .arg(
+            clap::Arg::new("allow_run")
+                .long("allow-run")
+                .help("Lets the script run other programs"),
+        )
+        .arg(
+            clap::Arg::new("ARGS")
+                .help(
+                    "Arguments passed to the script, everything after FILE is one of them \
+                     so the flags of micron must come before FILE",
+                )
+                .takes_value(true)
+                .multiple_values(true)
+                .allow_hyphen_values(true),
+        )
+        .trailing_var_arg(true);
+
+    let matches = app.get_matches();
 
     let (source, file_name) = if let Some(file_name) = matches.value_of("FILE") {
         match file_read(file_name) {
@@ -71,6 +155,21 @@ fn main() {
     let allow_debug = matches.is_present("debug");
     let prettify = matches.is_present("pretty");
     let compile_only = matches.is_present("compileonly");
+    let big_ints = cfg!(feature = "bigint") && matches.is_present("bigints");
+    let permissions = interpreter::Permissions {
+        read: dirs_of(&matches, "allow_read"),
+        write: dirs_of(&matches, "allow_write"),
+        env: matches.is_present("allow_env"),
+        run: matches.is_present("allow_run"),
+    };
+    let seed = matches.value_of("seed").map(|s| s.parse().unwrap());
+    let fake_clock = matches
+        .value_of("fake_clock")
+        .map(|s| s.parse::<i64>().unwrap());
+    let args = match matches.values_of("ARGS") {
+        Some(values) => values.map(String::from).collect(),
+        None => Vec::new(),
+    };
 
     macro_rules! debug {
         ($e:expr) => {
@@ -85,16 +184,15 @@ fn main() {
     }
 
     let mut files = SimpleFiles::new();
-    let file_id = files.add(file_name, &source);
-    let source_chars = scanner::Char::from_source(&source);
-    let token_infos = tokenizer::tokenize(source_chars);
+    let path = matches.value_of("FILE").map(Path::new);
+    let program = loader::load(&mut files, file_name, source, path, big_ints);
 
-    debug!(&token_infos);
+    debug!(&program);
 
-    let token_infos = match token_infos {
+    let (labels, instr_infos) = match program {
         Ok(ok) => ok,
 
-        Err(err) => {
+        Err(LoadError::TokenizerError(file_id, err)) => {
             match err {
                 TokenizerError::SyntaxError(ref info) => {
                     let diagnostic = Diagnostic::error()
@@ -113,17 +211,9 @@ fn main() {
             }
             exit(1);
         }
-    };
-
-    let instr_infos = parser::parse(token_infos);
 
-    debug!(&instr_infos);
-
-    let (labels, instr_infos) = match instr_infos {
-        Ok(ok) => ok,
-
-        Err(parse_error_info) => {
-            let parse_error = &parse_error_info.error;
+        Err(LoadError::ParseError(file_id, parse_error_info)) => {
+            let parse_error = &*parse_error_info.error;
             let line = &parse_error_info.line;
             let note = &parse_error_info.note;
             let start = line[0].start;
@@ -181,6 +271,122 @@ fn main() {
                     )));
                 }
 
+                ParseError::NameAlreadyDeclared {
+                    name,
+                    line: line_at,
+                } => {
+                    let line_at_start = line_at[0].start;
+                    let line_at_end = line_at[line_at.len() - 1].end;
+
+                    labels.push(
+                        Label::primary(file_id, start..end).with_message(format!(
+                            "Found multiple declarations of name `{}`",
+                            &name
+                        )),
+                    );
+
+                    labels.push(
+                        Label::secondary(file_id, line_at_start..line_at_end)
+                            .with_message(format!("The name `{}` is already declared here", &name)),
+                    );
+                }
+
+                ParseError::UndeclaredName(token_info) => {
+                    let start = token_info.start;
+                    let end = token_info.end;
+
+                    labels.push(
+                        Label::primary(file_id, start..end)
+                            .with_message(format!("Undeclared name `{}`", token_info.token)),
+                    );
+                }
+
+                ParseError::IncludeFailed(token_info) => {
+                    let start = token_info.start;
+                    let end = token_info.end;
+
+                    labels.push(
+                        Label::primary(file_id, start..end)
+                            .with_message(format!("Failed to include {}", token_info.token)),
+                    );
+                }
+
+                ParseError::IncludeCycle(token_info) => {
+                    let start = token_info.start;
+                    let end = token_info.end;
+
+                    labels.push(Label::primary(file_id, start..end).with_message(format!(
+                        "Including {} leads back to this file",
+                        token_info.token
+                    )));
+                }
+
+                ParseError::UndefinedMacro(token_info) => {
+                    let start = token_info.start;
+                    let end = token_info.end;
+
+                    labels.push(
+                        Label::primary(file_id, start..end)
+                            .with_message(format!("Undefined macro `{}`", token_info.token)),
+                    );
+                }
+
+                ParseError::MacroArgumentMismatch {
+                    token_info,
+                    got,
+                    expected,
+                } => {
+                    let start = token_info.start;
+                    let end = token_info.end;
+
+                    labels.push(Label::primary(file_id, start..end).with_message(format!(
+                        "Macro `{}` expected {} arguments, got {}",
+                        token_info.token, expected, got
+                    )));
+                }
+
+                ParseError::MacroAlreadyDefined {
+                    name,
+                    line: line_at,
+                } => {
+                    let line_at_start = line_at[0].start;
+                    let line_at_end = line_at[line_at.len() - 1].end;
+
+                    labels.push(
+                        Label::primary(file_id, start..end).with_message(format!(
+                            "Found multiple definitions of macro `{}`",
+                            &name
+                        )),
+                    );
+
+                    labels.push(
+                        Label::secondary(file_id, line_at_start..line_at_end)
+                            .with_message(format!("The macro `{}` is already defined here", &name)),
+                    );
+                }
+
+                ParseError::UnterminatedMacro(token_info) => {
+                    let start = token_info.start;
+                    let end = token_info.end;
+
+                    labels.push(Label::primary(file_id, start..end).with_message(format!(
+                        "EOF while scanning for the end of macro `{}`",
+                        token_info.token
+                    )));
+                }
+
+                ParseError::MacroRecursionLimit(token_info) => {
+                    let start = token_info.start;
+                    let end = token_info.end;
+
+                    labels.push(
+                        Label::primary(file_id, start..end).with_message(format!(
+                            "Macro `{}` expands too deeply",
+                            token_info.token
+                        )),
+                    );
+                }
+
                 ParseError::UnknownFunctionName(token_info) => {
                     let start = token_info.start;
                     let end = token_info.end;
@@ -193,6 +399,13 @@ fn main() {
                 }
             };
 
+            if let Some((body_start, body_end)) = macros::expansion_span(line) {
+                labels.push(
+                    Label::secondary(file_id, body_start..body_end)
+                        .with_message("Expanded from this macro body"),
+                );
+            }
+
             let notes = match note {
                 Some(s) => vec![s.to_string()],
                 None => vec![],
@@ -213,23 +426,55 @@ fn main() {
     };
 
     if !compile_only {
-        let mut stdout = |s| {
-            print!("{}", s);
+        // the output is written out before reading the input and before exiting
+        let out = RefCell::new(BufWriter::new(io::stdout()));
+
+        let mut stdout = |s: String| out.borrow_mut().write_all(s.as_bytes()).map_err(|_| ());
+        let mut flush = || out.borrow_mut().flush().map_err(|_| ());
+        let mut stderr = |s| {
+            let _ = out.borrow_mut().flush();
+            eprint!("{}", s);
             Ok(())
         };
         let mut stdin = || {
-            let _ = io::stdout().flush();
+            let _ = out.borrow_mut().flush();
             let mut s = String::new();
             match io::stdin().read_line(&mut s) {
-                Ok(_) => Ok(s),
+                Ok(0) => Ok(None),
+                Ok(_) => Ok(Some(s)),
                 Err(_) => Err(()),
             }
         };
 
-        let result = interpreter::interpret(labels, instr_infos, &mut stdout, &mut stdin);
+        let mut keychar = || {
+            let _ = out.borrow_mut().flush();
+            terminal::read_key().map_err(|_| ())
+        };
+
+        let mut clock: Box<dyn Clock> = match fake_clock {
+            Some(time) => Box::new(FakeClock::new(time)),
+            None => Box::new(SystemClock::default()),
+        };
+
+        let mut host = interpreter::Host {
+            stdout: &mut stdout,
+            stderr: &mut stderr,
+            flush: &mut flush,
+            stdin: &mut stdin,
+            keychar: &mut keychar,
+            clock: clock.as_mut(),
+        };
+        let options = interpreter::Options {
+            big_ints,
+            args,
+            permissions,
+            seed,
+        };
+        let result = interpreter::interpret(labels, instr_infos, &mut host, options);
+        let _ = out.borrow_mut().flush();
 
         match result {
-            Ok(_) => {}
+            Ok(code) => exit(code),
             Err(interpreter_error) => {
                 let error_info = &interpreter_error.error_info;
                 let instr_info = &interpreter_error.instr_info;
@@ -246,13 +491,36 @@ fn main() {
                         format!("Got a jump signal to an undefined label `{}`", &s)
                     }
                     interpreter::Error::NoSlotError => format!("No empty slot found"),
+                    interpreter::Error::OverflowError => {
+                        format!("Function `{}` overflowed the range of a number", fun)
+                    }
+                    interpreter::Error::ZeroDivisionError => {
+                        format!("Function `{}` divided by zero", fun)
+                    }
                     interpreter::Error::ValueError(val) => {
                         format!("Function `{}`, {} is a bad value", fun, val)
                     }
+                    interpreter::Error::IoError(path) => {
+                        format!("Function `{}` failed to access `{}`", fun, path)
+                    }
+                    interpreter::Error::ProcessError(program) => {
+                        format!("Function `{}` failed to run `{}`", fun, program)
+                    }
+                    interpreter::Error::PermissionError(..) => {
+                        format!("Function `{}` is not permitted", fun)
+                    }
                     interpreter::Error::Error(err) => format!("Err: {}", err),
                 };
 
-                let label = Label::primary(file_id, start..end).with_message(label_msg);
+                let mut labels =
+                    vec![Label::primary(instr_info.file_id, start..end).with_message(label_msg)];
+
+                if let Some((body_start, body_end)) = instr_info.expanded_from {
+                    labels.push(
+                        Label::secondary(instr_info.file_id, body_start..body_end)
+                            .with_message("Expanded from this macro body"),
+                    );
+                }
 
                 let notes = match &error_info.note {
                     Some(s) => vec![s.to_string()],
@@ -262,7 +530,7 @@ fn main() {
                 let diagnostic = Diagnostic::error()
                     .with_message(format!("{}", &error_info.error))
                     .with_code(format!("{}", &error_info.error.error_code()))
-                    .with_labels(vec![label])
+                    .with_labels(labels)
                     .with_notes(notes);
 
                 let writer = StandardStream::stderr(ColorChoice::Always);
@@ -1,21 +1,117 @@
+#[cfg(feature = "bigint")]
+use crate::bigint::BigInt;
 use crate::errors::Code;
+use crate::macros;
 use crate::tokenizer::{Token, TokenCheck, TokenInfo};
-use std::collections::HashMap;
+use std::collections::BTreeMap;
+use std::collections::{HashMap, HashSet};
 use std::fmt;
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum Value {
     Str(String),
     Int(isize),
+    Float(f64),
+    /// An Int that doesn't fit in an isize, only produced when big ints are enabled.
+    #[cfg(feature = "bigint")]
+    BigInt(BigInt),
+    List(Vec<Value>),
+    Map(BTreeMap<Key, Value>),
     None,
 }
 
+/// The key of a Map entry, Int keys are ordered before the Str ones.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Key {
+    Int(isize),
+    Str(String),
+}
+
+impl From<Key> for Value {
+    fn from(key: Key) -> Self {
+        match key {
+            Key::Int(int) => Value::Int(int),
+            Key::Str(s) => Value::Str(s),
+        }
+    }
+}
+
+impl Value {
+    /// All the values are truthy except for `0`, `0.0`, an empty Str, an empty List, an empty Map and None.
+    pub fn is_truthy(&self) -> bool {
+        use Value::*;
+        match *self {
+            Str(ref s) => !s.is_empty(),
+            Int(int) => int != 0,
+            Float(float) => float != 0.0,
+            #[cfg(feature = "bigint")]
+            BigInt(..) => true,
+            List(ref list) => !list.is_empty(),
+            Map(ref map) => !map.is_empty(),
+            None => false,
+        }
+    }
+
+    /// The name of the data type, as returned by `type:`.
+    pub fn type_name(&self) -> &'static str {
+        use Value::*;
+        match *self {
+            Str(..) => "Str",
+            Int(..) => "Int",
+            Float(..) => "Float",
+            #[cfg(feature = "bigint")]
+            BigInt(..) => "Int",
+            List(..) => "List",
+            Map(..) => "Map",
+            None => "None",
+        }
+    }
+
+    /// The value as it's written by `p:` and `w:`.
+    pub fn text(&self) -> String {
+        use Value::*;
+        match *self {
+            Str(ref s) => s.to_string(),
+            Int(int) => int.to_string(),
+            // always written with a fraction so that it's not mistaken for an Int
+            Float(float) => format!("{:?}", float),
+            #[cfg(feature = "bigint")]
+            BigInt(ref big) => big.to_string(),
+            List(ref list) => {
+                let items = list.iter().map(|v| v.repr()).collect::<Vec<String>>();
+                format!("[{}]", items.join(", "))
+            }
+            Map(ref map) => {
+                let items = map
+                    .iter()
+                    .map(|(k, v)| format!("{}: {}", Value::from(k.clone()).repr(), v.repr()))
+                    .collect::<Vec<String>>();
+                format!("{{{}}}", items.join(", "))
+            }
+            None => "None".to_string(),
+        }
+    }
+
+    /// Same as `text` but Strs are quoted, used for the items of a List or a Map.
+    fn repr(&self) -> String {
+        match *self {
+            Value::Str(ref s) => format!("{:?}", s),
+            _ => self.text(),
+        }
+    }
+}
+
 impl fmt::Display for Value {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         use Value::*;
         match *self {
             Str(ref s) => write!(f, "{:?} (an Str)", s),
             Int(i) => write!(f, "{} (an Int)", i),
+            Float(n) => write!(f, "{:?} (a Float)", n),
+            #[cfg(feature = "bigint")]
+            BigInt(ref n) => write!(f, "{} (an Int)", n),
+            List(..) => write!(f, "{} (a List)", self.text()),
+            Map(..) => write!(f, "{} (a Map)", self.text()),
             None => write!(f, "None"),
         }
     }
@@ -31,24 +127,96 @@ pub enum Expr {
 pub enum Fun {
     Set(Expr, Expr),
     Get(Expr),
+    LocalSet(Expr, Expr),
+    LocalGet(Expr),
     Write(Expr),
     Print(Expr),
+    WriteErr(Expr),
+    PrintErr(Expr),
     Add(Expr, Expr),
+    Sub(Expr, Expr),
+    Mul(Expr, Expr),
+    Div(Expr, Expr),
+    Mod(Expr, Expr),
+    Pow(Expr, Expr),
+    Abs(Expr),
+    Random(Expr, Expr),
+    Min(Expr, Expr),
+    Max(Expr, Expr),
     Jump(Expr),
     Equal(Expr, Expr),
+    Less(Expr, Expr),
+    Greater(Expr, Expr),
+    And(Expr, Expr),
+    Or(Expr, Expr),
+    Not(Expr),
     Convert(Expr),
     Extract(Expr, Expr),
+    Len(Expr),
+    List,
+    Push(Expr, Expr),
+    Pop(Expr),
+    At(Expr, Expr),
+    Put(Expr, Expr, Expr),
+    Map,
+    Del(Expr, Expr),
+    LocalPush(Expr, Expr),
+    LocalPop(Expr),
+    LocalPut(Expr, Expr, Expr),
+    LocalDel(Expr, Expr),
+    Has(Expr, Expr),
+    Keys(Expr),
+    ToFloat(Expr),
+    ToInt(Expr),
+    BitAnd(Expr, Expr),
+    BitOr(Expr, Expr),
+    BitXor(Expr, Expr),
+    BitNot(Expr),
+    Shl(Expr, Expr),
+    Shr(Expr, Expr),
+    TypeOf(Expr),
+    IsNone(Expr),
+    Repr(Expr),
+    ReadFile(Expr),
+    WriteFile(Expr, Expr),
+    AppendFile(Expr, Expr),
+    FileExists(Expr),
+    ListDir(Expr),
+    ArgCount,
+    Arg(Expr),
+    Env(Expr),
+    Run(Expr, Expr, Expr),
+    Slice(Expr, Expr, Expr),
+    Find(Expr, Expr),
+    Replace(Expr, Expr, Expr),
+    Split(Expr, Expr, Expr),
+    Trim(Expr),
+    Upper(Expr),
+    Lower(Expr),
+    Repeat(Expr, Expr),
+    StartsWith(Expr, Expr),
+    EndsWith(Expr, Expr),
     If(Expr, Expr),
+    IfElse(Expr, Expr, Expr),
     Input,
+    ReadAll,
+    Flush,
     KeyChar,
+    Millis,
+    Time,
+    Sleep(Expr),
+    Date(Expr, Expr),
     Text(Expr),
     Number(Expr),
     CatchError(Expr, Expr),
     ThrowError(Expr),
     Return(Expr),
-    FunJump(Expr),
+    FunJump(Expr, Vec<Expr>),
     EmptySlot,
-    Exit,
+    Exit(Option<Expr>),
+    Try(Expr),
+    EndTry,
+    Rethrow,
 }
 
 impl fmt::Display for Fun {
@@ -57,24 +225,98 @@ impl fmt::Display for Fun {
         match *self {
             Set(..) => write!(f, "s:"),
             Get(..) => write!(f, "g:"),
+            LocalSet(..) => write!(f, "ls:"),
+            LocalGet(..) => write!(f, "lg:"),
             Write(..) => write!(f, "w:"),
             Print(..) => write!(f, "p:"),
+            WriteErr(..) => write!(f, "ew:"),
+            PrintErr(..) => write!(f, "ep:"),
             Add(..) => write!(f, "a:"),
+            Sub(..) => write!(f, "sub:"),
+            Mul(..) => write!(f, "mul:"),
+            Div(..) => write!(f, "div:"),
+            Mod(..) => write!(f, "mod:"),
+            Pow(..) => write!(f, "pow:"),
+            Abs(..) => write!(f, "abs:"),
+            Random(..) => write!(f, "rand:"),
+            Min(..) => write!(f, "min:"),
+            Max(..) => write!(f, "max:"),
             Jump(..) => write!(f, "j:"),
             Equal(..) => write!(f, "e:"),
+            Less(..) => write!(f, "<:"),
+            Greater(..) => write!(f, ">:"),
+            And(..) => write!(f, "and:"),
+            Or(..) => write!(f, "or:"),
+            Not(..) => write!(f, "not:"),
             Convert(..) => write!(f, "c:"),
             Extract(..) => write!(f, "x:"),
+            Len(..) => write!(f, "len:"),
+            List => write!(f, "list"),
+            Push(..) => write!(f, "push:"),
+            Pop(..) => write!(f, "pop:"),
+            At(..) => write!(f, "at:"),
+            Put(..) => write!(f, "put:"),
+            Map => write!(f, "map"),
+            ToFloat(..) => write!(f, "float:"),
+            ToInt(..) => write!(f, "int:"),
+            BitAnd(..) => write!(f, "band:"),
+            BitOr(..) => write!(f, "bor:"),
+            BitXor(..) => write!(f, "bxor:"),
+            BitNot(..) => write!(f, "bnot:"),
+            Shl(..) => write!(f, "shl:"),
+            Shr(..) => write!(f, "shr:"),
+            TypeOf(..) => write!(f, "type:"),
+            IsNone(..) => write!(f, "none:"),
+            Repr(..) => write!(f, "repr:"),
+            ReadFile(..) => write!(f, "read:"),
+            WriteFile(..) => write!(f, "write:"),
+            AppendFile(..) => write!(f, "append:"),
+            FileExists(..) => write!(f, "exists:"),
+            ListDir(..) => write!(f, "dir:"),
+            ArgCount => write!(f, "argc"),
+            Arg(..) => write!(f, "arg:"),
+            Env(..) => write!(f, "env:"),
+            Run(..) => write!(f, "run:"),
+            Del(..) => write!(f, "del:"),
+            LocalPush(..) => write!(f, "lpush:"),
+            LocalPop(..) => write!(f, "lpop:"),
+            LocalPut(..) => write!(f, "lput:"),
+            LocalDel(..) => write!(f, "ldel:"),
+            Has(..) => write!(f, "has:"),
+            Keys(..) => write!(f, "keys:"),
+            Slice(..) => write!(f, "slice:"),
+            Find(..) => write!(f, "find:"),
+            Replace(..) => write!(f, "replace:"),
+            Split(..) => write!(f, "split:"),
+            Trim(..) => write!(f, "trim:"),
+            Upper(..) => write!(f, "upper:"),
+            Lower(..) => write!(f, "lower:"),
+            Repeat(..) => write!(f, "repeat:"),
+            StartsWith(..) => write!(f, "starts:"),
+            EndsWith(..) => write!(f, "ends:"),
             If(..) => write!(f, "?:"),
+            IfElse(..) => write!(f, "??:"),
             Input => write!(f, "i"),
+            ReadAll => write!(f, "stdin"),
+            Flush => write!(f, "flush"),
             KeyChar => write!(f, "k"),
+            Millis => write!(f, "ms"),
+            Time => write!(f, "time"),
+            Sleep(..) => write!(f, "sleep:"),
+            Date(..) => write!(f, "date:"),
             Text(..) => write!(f, "t:"),
             Number(..) => write!(f, "n:"),
             CatchError(..) => write!(f, "#:"),
             ThrowError(..) => write!(f, "!:"),
             Return(..) => write!(f, "r:"),
+            FunJump(_, ref args) if !args.is_empty() => write!(f, "f{}:", args.len()),
             FunJump(..) => write!(f, "f:"),
             EmptySlot => write!(f, "~"),
-            Exit => write!(f, "$"),
+            Exit(None) => write!(f, "$"),
+            Exit(Some(..)) => write!(f, "$:"),
+            Try(..) => write!(f, "try:"),
+            EndTry => write!(f, "endtry"),
+            Rethrow => write!(f, "rethrow"),
         }
     }
 }
@@ -85,6 +327,7 @@ pub enum Instr {
     SetLabel(String),
     LabelPlaceHolder(String),
     FunCall(Box<Fun>),
+    EndOfFile,
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -92,11 +335,20 @@ pub struct InstrInfo {
     pub instr: Instr,
     pub start: usize,
     pub end: usize,
+    pub file_id: usize,
+    /// The span of the macro body the instruction is expanded from, if any.
+    pub expanded_from: Option<(usize, usize)>,
 }
 
 impl InstrInfo {
-    fn new(instr: Instr, start: usize, end: usize) -> Self {
-        Self { instr, start, end }
+    pub fn new(instr: Instr, start: usize, end: usize) -> Self {
+        Self {
+            instr,
+            start,
+            end,
+            file_id: 0,
+            expanded_from: None,
+        }
     }
 }
 
@@ -115,6 +367,25 @@ pub enum ParseError {
         expected: usize,
     },
     UnknownFunctionName(TokenInfo),
+    NameAlreadyDeclared {
+        name: String,
+        line: Vec<TokenInfo>,
+    },
+    UndeclaredName(TokenInfo),
+    IncludeFailed(TokenInfo),
+    IncludeCycle(TokenInfo),
+    UndefinedMacro(TokenInfo),
+    MacroArgumentMismatch {
+        token_info: TokenInfo,
+        got: usize,
+        expected: usize,
+    },
+    MacroAlreadyDefined {
+        name: String,
+        line: Vec<TokenInfo>,
+    },
+    UnterminatedMacro(TokenInfo),
+    MacroRecursionLimit(TokenInfo),
 }
 
 impl ParseError {
@@ -126,6 +397,15 @@ impl ParseError {
             InvalidSyntax => 303,
             NotEnoughArgument { .. } => 304,
             UnknownFunctionName(..) => 305,
+            NameAlreadyDeclared { .. } => 306,
+            UndeclaredName(..) => 307,
+            IncludeFailed(..) => 308,
+            IncludeCycle(..) => 309,
+            UndefinedMacro(..) => 310,
+            MacroArgumentMismatch { .. } => 311,
+            MacroAlreadyDefined { .. } => 312,
+            UnterminatedMacro(..) => 313,
+            MacroRecursionLimit(..) => 314,
         })
     }
 }
@@ -139,28 +419,76 @@ impl fmt::Display for ParseError {
             InvalidSyntax => write!(f, "InvalidSyntax"),
             NotEnoughArgument { .. } => write!(f, "NotEnoughArgument"),
             UnknownFunctionName(..) => write!(f, "UnknownFunctionName"),
+            NameAlreadyDeclared { .. } => write!(f, "NameAlreadyDeclared"),
+            UndeclaredName(..) => write!(f, "UndeclaredName"),
+            IncludeFailed(..) => write!(f, "IncludeFailed"),
+            IncludeCycle(..) => write!(f, "IncludeCycle"),
+            UndefinedMacro(..) => write!(f, "UndefinedMacro"),
+            MacroArgumentMismatch { .. } => write!(f, "MacroArgumentMismatch"),
+            MacroAlreadyDefined { .. } => write!(f, "MacroAlreadyDefined"),
+            UnterminatedMacro(..) => write!(f, "UnterminatedMacro"),
+            MacroRecursionLimit(..) => write!(f, "MacroRecursionLimit"),
         }
     }
 }
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct ParseErrorInfo {
-    pub error: ParseError,
+    // boxed to keep the `Result`s small, the errors holding tokens are rather large
+    pub error: Box<ParseError>,
     pub line: Vec<TokenInfo>,
     pub note: Option<String>,
 }
 
 impl ParseErrorInfo {
-    fn new(error: ParseError, line: Vec<TokenInfo>, note: Option<String>) -> Self {
-        Self { error, line, note }
+    pub fn new(error: ParseError, line: Vec<TokenInfo>, note: Option<String>) -> Self {
+        Self {
+            error: Box::new(error),
+            line,
+            note,
+        }
     }
 }
 
+/// What the lines of a file may refer to, besides the built-in functions.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Scope {
+    names: HashMap<String, isize>,
+    namespace: Option<String>,
+    labels: HashSet<String>,
+}
+
+impl Scope {
+    /// Prefixes the label with the namespace of the file if it's defined in the file.
+    /// Labels of other files are left as they are, so `"foo.bar"` still refers to `;bar` of the module `foo`.
+    fn label(&self, label: &str) -> String {
+        match &self.namespace {
+            Some(namespace) if self.labels.contains(label) => format!("{}.{}", namespace, label),
+            _ => label.to_string(),
+        }
+    }
+
+    /// Same as `label` but for the (Str) label arguments of jump like functions.
+    fn label_expr(&self, expr: Expr) -> Expr {
+        match expr {
+            Expr::Value(Value::Str(s)) => Expr::Value(Value::Str(self.label(&s))),
+            _ => expr,
+        }
+    }
+}
+
+/// Parses the tokens of a file.
+/// When the file is included as a module, its labels are prefixed with the `namespace`.
+/// The slots picked for the names declared without one are added to `taken_slots`, so that the
+/// names of different files don't end up sharing slots.
 pub fn parse(
     token_infos: Vec<TokenInfo>,
+    namespace: Option<&str>,
+    taken_slots: &mut HashSet<isize>,
 ) -> Result<(HashMap<String, usize>, Vec<InstrInfo>), ParseErrorInfo> {
     use Token::*;
     let mut labels = HashMap::new();
+    let mut label_lines = HashMap::new();
     let mut instrs = Vec::new();
 
     let mut token_infos_lines: Vec<Vec<TokenInfo>> = Vec::new();
@@ -184,6 +512,21 @@ pub fn parse(
         }
     }
 
+    let mut scope = Scope {
+        names: declare_names(&token_infos_lines, taken_slots)?,
+        namespace: namespace.map(|s| s.to_string()),
+        labels: HashSet::new(),
+    };
+
+    // collected beforehand so that the jumps written before the label get the namespace as well
+    for line in token_infos_lines.iter() {
+        if line.len() == 2 && line[0].token == Smi {
+            if let Idn(idn) = &line[1].token {
+                scope.labels.insert(idn.to_string());
+            }
+        }
+    }
+
     for (n, line) in token_infos_lines.iter().enumerate() {
         let mut token_line = Vec::new();
 
@@ -196,13 +539,15 @@ pub fn parse(
             // ;idn
             [Smi, ..] => match &token_line[..] {
                 [Smi, Idn(idn)] => {
-                    match labels.get(idn) {
+                    let label = scope.label(idn);
+
+                    match label_lines.get(&label) {
                         Some(line_no) => {
                             let line_no: usize = *line_no;
                             let line_at = token_infos_lines[line_no].clone();
                             return Err(ParseErrorInfo::new(
                                 ParseError::LabelAlreadySet {
-                                    label: idn.to_string(),
+                                    label,
                                     line: line_at,
                                 },
                                 line.to_vec(),
@@ -211,9 +556,10 @@ pub fn parse(
                         }
 
                         None => {
-                            labels.insert(idn.to_string(), n);
+                            labels.insert(label.clone(), instrs.len());
+                            label_lines.insert(label.clone(), n);
                             instrs.push(InstrInfo::new(
-                                Instr::LabelPlaceHolder(idn.to_string()),
+                                Instr::LabelPlaceHolder(label),
                                 line[0].start,
                                 line[line.len() - 1].end,
                             ));
@@ -237,16 +583,26 @@ pub fn parse(
                 }
             },
 
+            // @idn, already declared by `declare_names`
+            [At, ..] => {}
+
+            // &"file", already included by the loader
+            [Amp, ..] => {}
+
             // idn:
-            [Idn(_) | Dol | Que | Eql | Not | Hsh, ..] => {
-                let (_i, expr) = parse_func_call(line.clone())?;
+            [Idn(_) | Dol | Que | Eql | Not | Hsh | Lt | Gt, ..] => {
+                let (_i, expr) = parse_func_call(line.clone(), &scope)?;
 
                 match expr {
-                    Expr::FunCall(fun) => instrs.push(InstrInfo::new(
-                        Instr::FunCall(fun),
-                        line[0].start,
-                        line[line.len() - 1].end,
-                    )),
+                    Expr::FunCall(fun) => {
+                        let mut instr_info = InstrInfo::new(
+                            Instr::FunCall(fun),
+                            line[0].start,
+                            line[line.len() - 1].end,
+                        );
+                        instr_info.expanded_from = macros::expansion_span(line);
+                        instrs.push(instr_info);
+                    }
 
                     _ => panic!("Got `{:?}`. (This error is not supposed to occur.)", expr),
                 }
@@ -265,7 +621,101 @@ pub fn parse(
     Ok((labels, instrs))
 }
 
-pub fn parse_dot_op(line: Vec<TokenInfo>) -> Result<(usize, Expr), ParseErrorInfo> {
+/// Collects the slot names declared with `@idn int`, or just `@idn` to let the parser pick the slot.
+/// The picked slots count down from -2 so that they neither collide with the slots returned by `~`
+/// nor with the slot -1 which holds the error code.
+fn declare_names(
+    token_infos_lines: &[Vec<TokenInfo>],
+    taken_slots: &mut HashSet<isize>,
+) -> Result<HashMap<String, isize>, ParseErrorInfo> {
+    let mut names = HashMap::new();
+    let mut name_lines: HashMap<String, usize> = HashMap::new();
+    let mut unnumbered = Vec::new();
+
+    for (n, line) in token_infos_lines.iter().enumerate() {
+        let mut token_line = Vec::new();
+
+        for token_info in line.iter() {
+            token_line.push(token_info.token.clone());
+        }
+
+        let (name, slot) = match &token_line[..] {
+            [Token::At, Token::Idn(idn)] => (idn, None),
+            [Token::At, Token::Idn(idn), Token::Int(int)] => (idn, Some(*int)),
+            [Token::At, ..] => {
+                let unexpected = match &token_line[..] {
+                    [Token::At] => line[0].clone(),
+                    [Token::At, Token::Idn(_), Token::Int(_), ..] => line[3].clone(),
+                    [Token::At, Token::Idn(_), ..] => line[2].clone(),
+                    _ => line[1].clone(),
+                };
+
+                return Err(ParseErrorInfo::new(
+                    ParseError::UnexpectedToken(unexpected),
+                    line.to_vec(),
+                    Some("Slot names are declared like `@foo` or `@foo 0`".to_string()),
+                ));
+            }
+            _ => continue,
+        };
+
+        if let Some(line_no) = name_lines.get(name) {
+            return Err(ParseErrorInfo::new(
+                ParseError::NameAlreadyDeclared {
+                    name: name.to_string(),
+                    line: token_infos_lines[*line_no].clone(),
+                },
+                line.to_vec(),
+                None,
+            ));
+        }
+
+        name_lines.insert(name.to_string(), n);
+
+        match slot {
+            Some(int) => {
+                names.insert(name.to_string(), int);
+            }
+            None => unnumbered.push(name.to_string()),
+        }
+    }
+
+    taken_slots.extend(names.values());
+    let mut slot = -2;
+
+    for name in unnumbered {
+        while taken_slots.contains(&slot) {
+            slot -= 1;
+        }
+
+        names.insert(name, slot);
+        taken_slots.insert(slot);
+    }
+
+    Ok(names)
+}
+
+/// Parses `@idn` to the slot number the name is bound to.
+pub fn parse_name(line: Vec<TokenInfo>, scope: &Scope) -> Result<(usize, Expr), ParseErrorInfo> {
+    match line.get(1).map(|token_info| &token_info.token) {
+        Some(Token::Idn(idn)) => match scope.names.get(idn) {
+            Some(int) => Ok((1, Expr::Value(Value::Int(*int)))),
+            None => Err(ParseErrorInfo::new(
+                ParseError::UndeclaredName(line[1].clone()),
+                line.clone(),
+                Some(format!("Declare it using `@{}`", idn)),
+            )),
+        },
+
+        _ => Err(ParseErrorInfo::new(
+            ParseError::UnexpectedToken(line.get(1).unwrap_or(&line[0]).clone()),
+            line,
+            None,
+        )),
+    }
+}
+
+pub fn parse_dot_op(line: Vec<TokenInfo>, scope: &Scope) -> Result<(usize, Expr), ParseErrorInfo> {
     let mut token_line = Vec::new();
 
     for tok_inf in line.iter() {
@@ -273,24 +723,48 @@ pub fn parse_dot_op(line: Vec<TokenInfo>) -> Result<(usize, Expr), ParseErrorInf
     }
 
     match &token_line[..] {
-        [Token::Dot, Token::Int(int), ..] => {
-            return Ok((
-                1,
-                Expr::FunCall(Box::new(Fun::Get(Expr::Value(Value::Int(*int))))),
-            ));
+        [Token::Dot, Token::Int(int), ..] => Ok((
+            1,
+            Expr::FunCall(Box::new(Fun::Get(Expr::Value(Value::Int(*int))))),
+        )),
+
+        [Token::Dot, Token::Dot, Token::Int(int), ..] => Ok((
+            2,
+            Expr::FunCall(Box::new(Fun::LocalGet(Expr::Value(Value::Int(*int))))),
+        )),
+
+        [Token::Dot, Token::Idn(_), ..] => {
+            let (_, slot) = parse_name(line, scope)?;
+            Ok((1, Expr::FunCall(Box::new(Fun::Get(slot)))))
         }
 
-        _ => {
-            return Err(ParseErrorInfo::new(
-                ParseError::UnexpectedToken(line[1].clone()),
-                line,
-                None,
-            ))
+        [Token::Dot, Token::Dot, Token::Idn(_), ..] => {
+            let (_, slot) = parse_name(line[1..].to_vec(), scope)?;
+            Ok((2, Expr::FunCall(Box::new(Fun::LocalGet(slot)))))
         }
+
+        _ => Err(ParseErrorInfo::new(
+            ParseError::UnexpectedToken(line[1].clone()),
+            line,
+            None,
+        )),
     }
 }
 
-pub fn parse_func_call(line: Vec<TokenInfo>) -> Result<(usize, Expr), ParseErrorInfo> {
+/// Returns the number of arguments passed to the routine for `f1:`, `f2:` and so on.
+pub(crate) fn fun_jump_arity(s: &str) -> Option<usize> {
+    match s.strip_prefix('f') {
+        Some(digits) if !digits.is_empty() && digits.chars().all(|c| c.is_ascii_digit()) => {
+            digits.parse::<usize>().ok()
+        }
+        _ => None,
+    }
+}
+
+pub fn parse_func_call(
+    line: Vec<TokenInfo>,
+    scope: &Scope,
+) -> Result<(usize, Expr), ParseErrorInfo> {
     let mut token_line = Vec::new();
     for tok_inf in line.iter() {
         token_line.push(tok_inf.token.clone());
@@ -300,22 +774,86 @@ pub fn parse_func_call(line: Vec<TokenInfo>) -> Result<(usize, Expr), ParseError
     let mut c = 0;
     let mut args = Vec::new();
 
+    // `??:` is the only function named with two tokens
+    let if_else = token_line[0] == Token::Que && token_line.get(1) == Some(&Token::Que);
+    if if_else {
+        i += 1;
+    }
+
     let count = match &token_line[0] {
         Token::Idn(s) => match s.as_str() {
             "s" => 2,
             "g" => 1,
             "w" => 1,
             "p" => 1,
+            "ew" => 1,
+            "ep" => 1,
             "a" => 2,
+            "sub" => 2,
+            "mul" => 2,
+            "div" => 2,
+            "mod" => 2,
+            "pow" => 2,
+            "abs" => 1,
+            "rand" => 2,
+            "min" => 2,
+            "max" => 2,
+            "and" => 2,
+            "or" => 2,
+            "not" => 1,
             "j" => 1,
             "c" => 1,
             "x" => 2,
+            "len" => 1,
+            "list" => 0,
+            "push" => 2,
+            "pop" => 1,
+            "at" => 2,
+            "put" => 3,
+            "map" => 0,
+            "float" => 1,
+            "int" => 1,
+            "band" | "bor" | "bxor" | "shl" | "shr" => 2,
+            "bnot" => 1,
+            "type" | "none" | "repr" => 1,
+            "read" | "exists" | "dir" => 1,
+            "write" | "append" => 2,
+            "argc" => 0,
+            "arg" | "env" => 1,
+            "run" => 3,
+            "del" => 2,
+            "lpush" | "ldel" => 2,
+            "lpop" => 1,
+            "lput" => 3,
+            "has" => 2,
+            "keys" => 1,
+            "slice" => 3,
+            "find" => 2,
+            "replace" => 3,
+            "split" => 3,
+            "trim" => 1,
+            "upper" => 1,
+            "lower" => 1,
+            "repeat" => 2,
+            "starts" => 2,
+            "ends" => 2,
             "i" => 0,
+            "stdin" => 0,
+            "flush" => 0,
             "k" => 0,
+            "ms" | "time" => 0,
+            "sleep" => 1,
+            "date" => 2,
             "n" => 1,
             "t" => 1,
             "f" => 1,
             "r" => 1,
+            "ls" => 2,
+            "lg" => 1,
+            "try" => 1,
+            "endtry" => 0,
+            "rethrow" => 0,
+            _ if fun_jump_arity(s).is_some() => 1 + fun_jump_arity(s).unwrap(),
             _ => {
                 return Err(ParseErrorInfo::new(
                     ParseError::UnknownFunctionName(line[0].clone()),
@@ -325,9 +863,14 @@ pub fn parse_func_call(line: Vec<TokenInfo>) -> Result<(usize, Expr), ParseError
             }
         },
 
+        Token::Que if if_else => 3,
         Token::Que => 2,
+        Token::Lt => 2,
+        Token::Gt => 2,
         Token::Eql => 2,
         Token::Hsh => 2,
+        // `$:` takes the exit code
+        Token::Dol if token_line.get(1) == Some(&Token::Col) => 1,
         Token::Dol => 0,
         Token::Til => 0,
         Token::Not => 1,
@@ -373,18 +916,34 @@ pub fn parse_func_call(line: Vec<TokenInfo>) -> Result<(usize, Expr), ParseError
 
                     Token::Int(int) => args.push(Expr::Value(Value::Int(*int))),
 
-                    Token::Idn(_) | Token::Eql | Token::Que | Token::Not | Token::Hsh => {
-                        let (_i, _args) = parse_func_call(line[c + i..].to_vec())?;
+                    Token::Float(float) => args.push(Expr::Value(Value::Float(*float))),
+
+                    #[cfg(feature = "bigint")]
+                    Token::BigInt(big) => args.push(Expr::Value(Value::BigInt(big.clone()))),
+
+                    Token::Idn(_)
+                    | Token::Eql
+                    | Token::Que
+                    | Token::Not
+                    | Token::Hsh
+                    | Token::Lt
+                    | Token::Gt
+                    | Token::Dol => {
+                        let (_i, _args) = parse_func_call(line[c + i..].to_vec(), scope)?;
                         args.push(_args);
                         i += _i;
                     }
 
                     Token::Til => args.push(Expr::FunCall(Box::new(Fun::EmptySlot))),
 
-                    Token::Dol => args.push(Expr::FunCall(Box::new(Fun::Exit))),
-
                     Token::Dot => {
-                        let (_i, _args) = parse_dot_op(line[c + i..].to_vec())?;
+                        let (_i, _args) = parse_dot_op(line[c + i..].to_vec(), scope)?;
+                        args.push(_args);
+                        i += _i;
+                    }
+
+                    Token::At => {
+                        let (_i, _args) = parse_name(line[c + i..].to_vec(), scope)?;
                         args.push(_args);
                         i += _i;
                     }
@@ -422,16 +981,88 @@ pub fn parse_func_call(line: Vec<TokenInfo>) -> Result<(usize, Expr), ParseError
                 "g" => Fun::Get(args[0].clone()),
                 "w" => Fun::Write(args[0].clone()),
                 "p" => Fun::Print(args[0].clone()),
+                "ew" => Fun::WriteErr(args[0].clone()),
+                "ep" => Fun::PrintErr(args[0].clone()),
                 "a" => Fun::Add(args[0].clone(), args[1].clone()),
-                "j" => Fun::Jump(args[0].clone()),
+                "sub" => Fun::Sub(args[0].clone(), args[1].clone()),
+                "mul" => Fun::Mul(args[0].clone(), args[1].clone()),
+                "div" => Fun::Div(args[0].clone(), args[1].clone()),
+                "mod" => Fun::Mod(args[0].clone(), args[1].clone()),
+                "pow" => Fun::Pow(args[0].clone(), args[1].clone()),
+                "abs" => Fun::Abs(args[0].clone()),
+                "rand" => Fun::Random(args[0].clone(), args[1].clone()),
+                "min" => Fun::Min(args[0].clone(), args[1].clone()),
+                "max" => Fun::Max(args[0].clone(), args[1].clone()),
+                "and" => Fun::And(args[0].clone(), args[1].clone()),
+                "or" => Fun::Or(args[0].clone(), args[1].clone()),
+                "not" => Fun::Not(args[0].clone()),
+                "j" => Fun::Jump(scope.label_expr(args[0].clone())),
                 "c" => Fun::Convert(args[0].clone()),
                 "x" => Fun::Extract(args[0].clone(), args[1].clone()),
+                "len" => Fun::Len(args[0].clone()),
+                "list" => Fun::List,
+                "push" => Fun::Push(args[0].clone(), args[1].clone()),
+                "pop" => Fun::Pop(args[0].clone()),
+                "at" => Fun::At(args[0].clone(), args[1].clone()),
+                "put" => Fun::Put(args[0].clone(), args[1].clone(), args[2].clone()),
+                "map" => Fun::Map,
+                "float" => Fun::ToFloat(args[0].clone()),
+                "int" => Fun::ToInt(args[0].clone()),
+                "band" => Fun::BitAnd(args[0].clone(), args[1].clone()),
+                "bor" => Fun::BitOr(args[0].clone(), args[1].clone()),
+                "bxor" => Fun::BitXor(args[0].clone(), args[1].clone()),
+                "bnot" => Fun::BitNot(args[0].clone()),
+                "shl" => Fun::Shl(args[0].clone(), args[1].clone()),
+                "shr" => Fun::Shr(args[0].clone(), args[1].clone()),
+                "type" => Fun::TypeOf(args[0].clone()),
+                "none" => Fun::IsNone(args[0].clone()),
+                "repr" => Fun::Repr(args[0].clone()),
+                "read" => Fun::ReadFile(args[0].clone()),
+                "write" => Fun::WriteFile(args[0].clone(), args[1].clone()),
+                "append" => Fun::AppendFile(args[0].clone(), args[1].clone()),
+                "exists" => Fun::FileExists(args[0].clone()),
+                "dir" => Fun::ListDir(args[0].clone()),
+                "argc" => Fun::ArgCount,
+                "arg" => Fun::Arg(args[0].clone()),
+                "env" => Fun::Env(args[0].clone()),
+                "run" => Fun::Run(args[0].clone(), args[1].clone(), args[2].clone()),
+                "del" => Fun::Del(args[0].clone(), args[1].clone()),
+                "lpush" => Fun::LocalPush(args[0].clone(), args[1].clone()),
+                "lpop" => Fun::LocalPop(args[0].clone()),
+                "lput" => Fun::LocalPut(args[0].clone(), args[1].clone(), args[2].clone()),
+                "ldel" => Fun::LocalDel(args[0].clone(), args[1].clone()),
+                "has" => Fun::Has(args[0].clone(), args[1].clone()),
+                "keys" => Fun::Keys(args[0].clone()),
+                "slice" => Fun::Slice(args[0].clone(), args[1].clone(), args[2].clone()),
+                "find" => Fun::Find(args[0].clone(), args[1].clone()),
+                "replace" => Fun::Replace(args[0].clone(), args[1].clone(), args[2].clone()),
+                "split" => Fun::Split(args[0].clone(), args[1].clone(), args[2].clone()),
+                "trim" => Fun::Trim(args[0].clone()),
+                "upper" => Fun::Upper(args[0].clone()),
+                "lower" => Fun::Lower(args[0].clone()),
+                "repeat" => Fun::Repeat(args[0].clone(), args[1].clone()),
+                "starts" => Fun::StartsWith(args[0].clone(), args[1].clone()),
+                "ends" => Fun::EndsWith(args[0].clone(), args[1].clone()),
                 "i" => Fun::Input,
+                "stdin" => Fun::ReadAll,
+                "flush" => Fun::Flush,
                 "k" => Fun::KeyChar,
+                "ms" => Fun::Millis,
+                "time" => Fun::Time,
+                "sleep" => Fun::Sleep(args[0].clone()),
+                "date" => Fun::Date(args[0].clone(), args[1].clone()),
                 "n" => Fun::Number(args[0].clone()),
                 "t" => Fun::Text(args[0].clone()),
-                "f" => Fun::FunJump(args[0].clone()),
+                "f" => Fun::FunJump(scope.label_expr(args[0].clone()), Vec::new()),
+                "ls" => Fun::LocalSet(args[0].clone(), args[1].clone()),
+                "lg" => Fun::LocalGet(args[0].clone()),
                 "r" => Fun::Return(args[0].clone()),
+                "try" => Fun::Try(scope.label_expr(args[0].clone())),
+                "endtry" => Fun::EndTry,
+                "rethrow" => Fun::Rethrow,
+                _ if fun_jump_arity(s).is_some() => {
+                    Fun::FunJump(scope.label_expr(args[0].clone()), args[1..].to_vec())
+                }
                 _ => panic!(
                     "Got an unknow function name `{}`. (This error is not supposed to occur.)",
                     s
@@ -439,10 +1070,13 @@ pub fn parse_func_call(line: Vec<TokenInfo>) -> Result<(usize, Expr), ParseError
             }
         }
 
+        Token::Que if if_else => Fun::IfElse(args[0].clone(), args[1].clone(), args[2].clone()),
         Token::Que => Fun::If(args[0].clone(), args[1].clone()),
+        Token::Lt => Fun::Less(args[0].clone(), args[1].clone()),
+        Token::Gt => Fun::Greater(args[0].clone(), args[1].clone()),
         Token::Eql => Fun::Equal(args[0].clone(), args[1].clone()),
-        Token::Hsh => Fun::CatchError(args[0].clone(), args[1].clone()),
-        Token::Dol => Fun::Exit,
+        Token::Hsh => Fun::CatchError(scope.label_expr(args[0].clone()), args[1].clone()),
+        Token::Dol => Fun::Exit(args.first().cloned()),
         Token::Til => Fun::EmptySlot,
         Token::Not => Fun::ThrowError(args[0].clone()),
         _ => panic!(
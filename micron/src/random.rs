@@ -0,0 +1,52 @@
+/// A small xorshift64* generator, which gives the same numbers for the same seed
+/// so that the runs can be reproduced. It's not meant for anything that has to be unpredictable.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        // the seed is scrambled (with splitmix64) so that close seeds give unrelated numbers,
+        // and so that zero, which xorshift never leaves, isn't a problem
+        let mut z = seed.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^= z >> 31;
+
+        Self {
+            state: if z == 0 { 0x9e37_79b9_7f4a_7c15 } else { z },
+        }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state ^= self.state >> 12;
+        self.state ^= self.state << 25;
+        self.state ^= self.state >> 27;
+        self.state.wrapping_mul(0x2545_f491_4f6c_dd1d)
+    }
+
+    /// An int between `low` and `high`, both included, each being equally likely.
+    /// Returns None if `low` is greater than `high`.
+    pub fn range(&mut self, low: isize, high: isize) -> Option<isize> {
+        if low > high {
+            return None;
+        }
+
+        let span = (high as i128 - low as i128) as u128 + 1;
+        if span > u64::MAX as u128 {
+            return Some(self.next_u64() as isize);
+        }
+        let span = span as u64;
+
+        // the lowest numbers are skipped so that what's left divides evenly by `span`,
+        // else the smaller results would come up a little more often
+        let skip = (u64::MAX % span + 1) % span;
+        loop {
+            let n = self.next_u64();
+            if n >= skip {
+                return Some((low as i128 + (n % span) as i128) as isize);
+            }
+        }
+    }
+}
@@ -0,0 +1,108 @@
+use std::io::{self, Read};
+
+/// Reads a single keystroke from the stdin, None at the end of the input.
+/// On a Linux terminal the key is taken as soon as it's pressed, without echoing it,
+/// otherwise (say the input is piped) it's just the next char of the input.
+pub fn read_key() -> io::Result<Option<char>> {
+    #[cfg(target_os = "linux")]
+    {
+        if let Some(raw) = linux::RawMode::enable() {
+            let key = read_char();
+
+            // the raw mode doesn't let the terminal turn Ctrl-C into a signal, so it's raised
+            // here after the settings are restored (by dropping `raw`) to keep its usual meaning
+            if let Ok(Some('\u{3}')) = key {
+                drop(raw);
+                linux::interrupt();
+            }
+
+            return key;
+        }
+    }
+
+    read_char()
+}
+
+/// Reads the bytes of one UTF-8 encoded char.
+fn read_char() -> io::Result<Option<char>> {
+    let mut stdin = io::stdin();
+    let mut bytes = [0u8; 4];
+
+    if stdin.read(&mut bytes[..1])? == 0 {
+        return Ok(None);
+    }
+
+    // a byte that can't start a char is refused right away,
+    // rather than waiting for continuation bytes that may never come
+    let len = match bytes[0] {
+        0x00..=0x7f => 1,
+        0xc2..=0xdf => 2,
+        0xe0..=0xef => 3,
+        0xf0..=0xf4 => 4,
+        byte => {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("{:#04x} can't start a UTF-8 encoded char", byte),
+            ))
+        }
+    };
+    stdin.read_exact(&mut bytes[1..len])?;
+
+    match std::str::from_utf8(&bytes[..len]) {
+        Ok(s) => Ok(s.chars().next()),
+        Err(err) => Err(io::Error::new(io::ErrorKind::InvalidData, err)),
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod linux {
+    use std::mem::MaybeUninit;
+
+    /// The terminal in non-canonical mode without echo, restored to its previous settings on drop,
+    /// so it's restored even if reading the key fails.
+    pub struct RawMode {
+        original: libc::termios,
+    }
+
+    impl RawMode {
+        /// None if the stdin is not a terminal.
+        pub fn enable() -> Option<Self> {
+            unsafe {
+                if libc::isatty(libc::STDIN_FILENO) != 1 {
+                    return None;
+                }
+
+                let mut original = MaybeUninit::<libc::termios>::uninit();
+                if libc::tcgetattr(libc::STDIN_FILENO, original.as_mut_ptr()) != 0 {
+                    return None;
+                }
+                let original = original.assume_init();
+
+                let mut raw = original;
+                raw.c_lflag &= !(libc::ICANON | libc::ECHO | libc::ISIG);
+                raw.c_cc[libc::VMIN] = 1;
+                raw.c_cc[libc::VTIME] = 0;
+
+                if libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, &raw) != 0 {
+                    return None;
+                }
+
+                Some(Self { original })
+            }
+        }
+    }
+
+    impl Drop for RawMode {
+        fn drop(&mut self) {
+            unsafe {
+                libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, &self.original);
+            }
+        }
+    }
+
+    pub fn interrupt() {
+        unsafe {
+            libc::raise(libc::SIGINT);
+        }
+    }
+}
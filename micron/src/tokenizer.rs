@@ -1,3 +1,5 @@
+#[cfg(feature = "bigint")]
+use crate::bigint::BigInt;
 use crate::errors::Code;
 use crate::scanner;
 use scanner::Char;
@@ -7,6 +9,9 @@ use std::fmt;
 pub enum Token {
     Str(String), // String Literal
     Int(isize),  // Integer
+    Float(f64),  // Floating-point number
+    #[cfg(feature = "bigint")]
+    BigInt(BigInt), // Integer too large for an isize
     Idn(String), // Identifier
     Til,         // ~
     Col,         // :
@@ -18,6 +23,12 @@ pub enum Token {
     Eql,         // =
     Not,         // !
     Hsh,         // #
+    At,          // @
+    Amp,         // &
+    Pct,         // %
+    Com,         // ,
+    Lt,          // <
+    Gt,          // >
 }
 
 impl fmt::Display for Token {
@@ -26,6 +37,9 @@ impl fmt::Display for Token {
         match self {
             Str(s) => write!(f, "{:?}", s),
             Int(i) => write!(f, "{}", i),
+            Float(n) => write!(f, "{:?}", n),
+            #[cfg(feature = "bigint")]
+            BigInt(n) => write!(f, "{}", n),
             Idn(s) => write!(f, "{}", s),
             Til => write!(f, "~"),
             Col => write!(f, ":"),
@@ -37,6 +51,12 @@ impl fmt::Display for Token {
             Eql => write!(f, "="),
             Not => write!(f, "!"),
             Hsh => write!(f, "#"),
+            At => write!(f, "@"),
+            Amp => write!(f, "&"),
+            Pct => write!(f, "%"),
+            Com => write!(f, ","),
+            Lt => write!(f, "<"),
+            Gt => write!(f, ">"),
         }
     }
 }
@@ -46,11 +66,19 @@ pub struct TokenInfo {
     pub token: Token,
     pub start: usize,
     pub end: usize,
+    /// The token as it's written in the body of the macro, if it's produced by a macro expansion.
+    /// `start` and `end` then point at the place the macro is used.
+    pub expanded_from: Option<Box<TokenInfo>>,
 }
 
 impl TokenInfo {
     pub fn new(start: usize, end: usize, token: Token) -> Self {
-        Self { token, start, end }
+        Self {
+            token,
+            start,
+            end,
+            expanded_from: None,
+        }
     }
 
     #[allow(dead_code)]
@@ -62,6 +90,7 @@ impl TokenInfo {
             token,
             start: first.n,
             end: last.n,
+            expanded_from: None,
         }
     }
 }
@@ -96,7 +125,8 @@ pub struct ErrorInfo {
     pub msg: Option<String>,
 }
 
-pub fn tokenize(chars: Vec<Char>) -> Result<Vec<TokenInfo>, TokenizerError> {
+/// Integer literals too large for an isize are only accepted with `big_ints`.
+pub fn tokenize(chars: Vec<Char>, big_ints: bool) -> Result<Vec<TokenInfo>, TokenizerError> {
     let mut tokens = Vec::new();
     let mut i = 0;
 
@@ -112,6 +142,12 @@ pub fn tokenize(chars: Vec<Char>) -> Result<Vec<TokenInfo>, TokenizerError> {
             '=' => tokens.push(TokenInfo::new(i, i, Token::Eql)),
             '!' => tokens.push(TokenInfo::new(i, i, Token::Not)),
             '#' => tokens.push(TokenInfo::new(i, i, Token::Hsh)),
+            '@' => tokens.push(TokenInfo::new(i, i, Token::At)),
+            '&' => tokens.push(TokenInfo::new(i, i, Token::Amp)),
+            '%' => tokens.push(TokenInfo::new(i, i, Token::Pct)),
+            ',' => tokens.push(TokenInfo::new(i, i, Token::Com)),
+            '<' => tokens.push(TokenInfo::new(i, i, Token::Lt)),
+            '>' => tokens.push(TokenInfo::new(i, i, Token::Gt)),
             ' ' => {}
 
             'a'..='z' | 'A'..='Z' | '_' => {
@@ -131,14 +167,53 @@ pub fn tokenize(chars: Vec<Char>) -> Result<Vec<TokenInfo>, TokenizerError> {
                 i -= 1;
             }
 
+            '0'..='9' | '-' if radix_of(&chars, i).is_some() => {
+                let j = i;
+                let (radix, prefix) = radix_of(&chars, i).unwrap();
+                i += prefix;
+                let mut temp = Vec::new();
+
+                while i < chars.len() && chars[i].char.is_ascii_alphanumeric() {
+                    temp.push(chars[i]);
+                    i += 1;
+                }
+
+                let mut col = Char::extract(&temp).iter().collect::<String>();
+                if chars[j].char == '-' {
+                    col.insert(0, '-');
+                }
+
+                let num = match isize::from_str_radix(&col, radix) {
+                    Ok(ok) => ok,
+                    Err(_) => {
+                        return Err(TokenizerError::SyntaxError(ErrorInfo {
+                            start: j,
+                            end: i,
+                            msg: Some(format!("Invalid base {} literal", radix)),
+                        }))
+                    }
+                };
+
+                tokens.push(TokenInfo::new(j, i, Token::Int(num)));
+                i -= 1;
+            }
+
             '0'..='9' | '-' => {
                 let j = i;
                 i += 1;
                 let mut temp = vec![chars[j]];
+                let mut is_float = false;
 
                 while i < chars.len() {
                     match chars[i].char {
                         '0'..='9' => temp.push(chars[i]),
+                        // a single dot followed by a digit makes it a Float, like `1.5`
+                        '.' if !is_float
+                            && matches!(chars.get(i + 1), Some(c) if c.char.is_ascii_digit()) =>
+                        {
+                            is_float = true;
+                            temp.push(chars[i]);
+                        }
                         'a'..='z' | 'A'..='Z' => {
                             return Err(TokenizerError::SyntaxError(ErrorInfo {
                                 start: j,
@@ -153,18 +228,34 @@ pub fn tokenize(chars: Vec<Char>) -> Result<Vec<TokenInfo>, TokenizerError> {
 
                 let col = Char::extract(&temp).iter().collect::<String>();
 
-                let num = match col.parse::<isize>() {
-                    Ok(ok) => ok,
-                    Err(_) => {
-                        return Err(TokenizerError::SyntaxError(ErrorInfo {
-                            start: j,
-                            end: i,
-                            msg: Some("Invalid isize".to_string()),
-                        }))
+                let token = if is_float {
+                    match col.parse::<f64>() {
+                        Ok(ok) => Token::Float(ok),
+                        Err(_) => {
+                            return Err(TokenizerError::SyntaxError(ErrorInfo {
+                                start: j,
+                                end: i,
+                                msg: Some("Invalid float".to_string()),
+                            }))
+                        }
+                    }
+                } else {
+                    match col.parse::<isize>() {
+                        Ok(ok) => Token::Int(ok),
+                        Err(_) => match big_int(&col, big_ints) {
+                            Some(token) => token,
+                            None => {
+                                return Err(TokenizerError::SyntaxError(ErrorInfo {
+                                    start: j,
+                                    end: i,
+                                    msg: Some("Invalid isize".to_string()),
+                                }))
+                            }
+                        },
                     }
                 };
 
-                tokens.push(TokenInfo::new(j, i, Token::Int(num)));
+                tokens.push(TokenInfo::new(j, i, token));
                 i -= 1;
             }
 
@@ -282,3 +373,31 @@ impl TokenCheck {
         true
     }
 }
+
+#[cfg(feature = "bigint")]
+fn big_int(col: &str, big_ints: bool) -> Option<Token> {
+    if big_ints {
+        BigInt::parse(col).map(Token::BigInt)
+    } else {
+        None
+    }
+}
+
+#[cfg(not(feature = "bigint"))]
+fn big_int(_col: &str, _big_ints: bool) -> Option<Token> {
+    None
+}
+
+/// The radix and the prefix length of a hex (`0xFF`) or a binary (`0b1010`) literal starting at `i`.
+fn radix_of(chars: &[Char], i: usize) -> Option<(u32, usize)> {
+    let sign = (chars[i].char == '-') as usize;
+
+    match (chars.get(i + sign), chars.get(i + sign + 1)) {
+        (Some(zero), Some(c)) if zero.char == '0' => match c.char {
+            'x' | 'X' => Some((16, sign + 2)),
+            'b' | 'B' => Some((2, sign + 2)),
+            _ => None,
+        },
+        _ => None,
+    }
+}